@@ -0,0 +1,144 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::path::Path;
+
+use rust_snippets::simd::{self, Lanes};
+use rust_snippets::{effect, palette, particles};
+
+// CPU cost of one frame of every effect at a few particle counts, written into plain host
+// buffers, of the scalar `evaluate` against the `simd` lanes, of gradient lookups for every
+// palette in the library and of the 4 and 8 lane `simd` sin/cos against std. `cargo bench`, the numbers
+// land in target/criterion.
+
+const PARTICLE_COUNTS: &[u32] = &[10_000, 100_000, 400_000];
+const LANES_COUNT: u32 = 100_000;
+const SAMPLE_COUNTS: &[u32] = &[1_000, 100_000];
+const TRIG_COUNT: u32 = 100_000;
+
+// a frame well into the effects so nothing sits at its starting pose
+const TIME: f32 = 3.5;
+
+fn settings(particles: Option<u32>) -> effect::Settings {
+    effect::Settings {
+        palettes: palette::Library::load(Path::new(palette::DEFAULT_PATH)).expect("palette library"),
+        particles: particles,
+        ..Default::default()
+    }
+}
+
+fn update(c: &mut Criterion, parallel: bool) {
+    common::random::set_seed(1);
+    for name in effect::names() {
+        let mode = if parallel { "parallel" } else { "serial" };
+        let mut group = c.benchmark_group(format!("update/{}/{}", name, mode));
+        for &count in PARTICLE_COUNTS {
+            let effect = effect::create(name, &settings(Some(count))).expect("effect");
+            let capacity = effect.capacity();
+            let mut positions = vec![0.0f32; capacity as usize * 4];
+            let mut colors = vec![0.0f32; capacity as usize * 4];
+
+            group.throughput(Throughput::Elements(capacity as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(capacity), &capacity, |b, _| {
+                b.iter(|| {
+                    let mut writer = particles::ParticleWriter::new(&mut positions, &mut colors);
+                    writer.set_parallel(parallel);
+                    effect.update(&mut writer, black_box(TIME));
+                    writer.count()
+                });
+            });
+        }
+        group.finish();
+    }
+}
+
+fn update_serial(c: &mut Criterion) {
+    update(c, false);
+}
+
+fn update_parallel(c: &mut Criterion) {
+    update(c, true);
+}
+
+// one frame on the calling thread, a particle at a time through `evaluate` and
+// `simd::LANES` at a time through `update`
+fn lanes(c: &mut Criterion) {
+    common::random::set_seed(1);
+    for name in effect::names() {
+        let effect = effect::create(name, &settings(Some(LANES_COUNT))).expect("effect");
+        let capacity = effect.capacity();
+        let mut positions = vec![0.0f32; capacity as usize * 4];
+        let mut colors = vec![0.0f32; capacity as usize * 4];
+
+        let mut group = c.benchmark_group(format!("lanes/{}", name));
+        group.throughput(Throughput::Elements(capacity as u64));
+        group.bench_function(BenchmarkId::new("scalar", capacity), |b| {
+            b.iter(|| {
+                let mut writer = particles::ParticleWriter::new(&mut positions, &mut colors);
+                for index in 0..capacity {
+                    let state = effect.evaluate(black_box(TIME), index);
+                    writer.set_position(index as usize, state.posscale);
+                    writer.set_color(index as usize, state.color);
+                }
+                writer.count()
+            });
+        });
+        group.bench_function(BenchmarkId::new("simd", capacity), |b| {
+            b.iter(|| {
+                let mut writer = particles::ParticleWriter::new(&mut positions, &mut colors);
+                writer.set_parallel(false);
+                effect.update(&mut writer, black_box(TIME));
+                writer.count()
+            });
+        });
+        group.finish();
+    }
+}
+
+fn gradient_sample(c: &mut Criterion) {
+    let library = settings(None).palettes;
+    for name in library.names() {
+        let gradient = library.get(name).expect("listed palette");
+        let mut group = c.benchmark_group(format!("gradient/{}", name));
+        for &count in SAMPLE_COUNTS {
+            group.throughput(Throughput::Elements(count as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+                b.iter(|| {
+                    let mut sum = 0.0;
+                    for i in 0..count {
+                        sum += gradient.sample(black_box(common::random::random(1, i)))[0];
+                    }
+                    sum
+                });
+            });
+        }
+        group.finish();
+    }
+}
+
+fn trig(c: &mut Criterion) {
+    // arguments in the range the effects produce
+    let args: Vec<f32> = (0..TRIG_COUNT).map(|i| common::random::random_signed(1, i) * 100.0).collect();
+    let mut group = c.benchmark_group("trig");
+    group.throughput(Throughput::Elements(TRIG_COUNT as u64));
+    group.bench_function(BenchmarkId::new("std", TRIG_COUNT), |b| {
+        b.iter(|| black_box(&args).iter().map(|x| x.sin() + x.cos()).sum::<f32>());
+    });
+    group.bench_function(BenchmarkId::new("simd4", TRIG_COUNT), |b| {
+        b.iter(|| trig_lanes::<simd::F32x4>(black_box(&args)));
+    });
+    group.bench_function(BenchmarkId::new("simd8", TRIG_COUNT), |b| {
+        b.iter(|| trig_lanes::<simd::F32x8>(black_box(&args)));
+    });
+    group.finish();
+}
+
+fn trig_lanes<R: Lanes>(args: &[f32]) -> f32 {
+    let mut sum = R::splat(0.0);
+    for x in args.chunks_exact(R::LANES) {
+        let x = R::from_fn(|lane| x[lane]);
+        sum = sum + x.sin() + x.cos();
+    }
+    (0..R::LANES).map(|lane| sum.lane(lane)).sum()
+}
+
+criterion_group!(benches, update_serial, update_parallel, lanes, gradient_sample, trig);
+criterion_main!(benches);
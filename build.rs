@@ -1,10 +1,21 @@
 use spirv_builder::{MemoryModel, SpirvBuilder};
 use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+// shader crates, each one becomes a `spirv::<NAME>` constant holding its module
+const SHADERS: &[(&str, &str)] = &[("SHADER", "shader")];
 
 fn main() -> Result<(), Box<dyn Error>> {
-    SpirvBuilder::new("shader")
-        .spirv_version(1, 0)
-        .memory_model(MemoryModel::GLSL450)
-        .build()?;
+    let mut generated = String::new();
+    for (name, path) in SHADERS {
+        let module = SpirvBuilder::new(path)
+            .spirv_version(1, 0)
+            .memory_model(MemoryModel::GLSL450)
+            .build()?;
+        writeln!(generated, "pub const {}: &[u8] = include_bytes!({:?});", name, module.to_string_lossy())?;
+    }
+    let out_dir = std::env::var("OUT_DIR")?;
+    std::fs::write(Path::new(&out_dir).join("spirv.rs"), generated)?;
     Ok(())
 }
@@ -0,0 +1,110 @@
+// std140 layout of the types used in uniform blocks, and vertex attribute descriptions.
+// `#[derive(UniformBlock)]` checks a `repr(C)` struct against std140 at compile time,
+// `#[derive(VertexLayout)]` describes the attributes of a vertex buffer binding.
+
+pub use common_derive::{UniformBlock, VertexLayout};
+
+pub trait Std140 {
+    const ALIGN: usize;
+    const SIZE: usize;
+}
+
+impl Std140 for f32 {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+}
+
+impl Std140 for u32 {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+}
+
+impl Std140 for flink::f32x4 {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 16;
+}
+
+impl Std140 for flink::f32x4x4 {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 64;
+}
+
+pub const fn align_up(offset: usize, align: usize) -> usize {
+    (offset + align - 1) / align * align
+}
+
+// `repr(C)` struct laid out like the std140 block in the shader, `Std140::SIZE` is the
+// size to bind
+pub trait UniformBlock: Std140 + Copy {
+    // fields in declaration order, compared against the block members of the SPIR-V
+    const FIELDS: &'static [BlockField];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockField {
+    pub name: &'static str,
+    // std140 offset in bytes
+    pub offset: usize,
+}
+
+// same names as `grr::VertexFormat`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VertexFormat {
+    X32Float,
+    Xy32Float,
+    Xyz32Float,
+    Xyzw32Float,
+}
+
+impl VertexFormat {
+    pub fn components(self) -> u32 {
+        match self {
+            VertexFormat::X32Float => 1,
+            VertexFormat::Xy32Float => 2,
+            VertexFormat::Xyz32Float => 3,
+            VertexFormat::Xyzw32Float => 4,
+        }
+    }
+}
+
+pub trait VertexAttributeFormat {
+    const FORMAT: VertexFormat;
+}
+
+impl VertexAttributeFormat for f32 {
+    const FORMAT: VertexFormat = VertexFormat::X32Float;
+}
+
+impl VertexAttributeFormat for [f32; 2] {
+    const FORMAT: VertexFormat = VertexFormat::Xy32Float;
+}
+
+impl VertexAttributeFormat for [f32; 3] {
+    const FORMAT: VertexFormat = VertexFormat::Xyz32Float;
+}
+
+impl VertexAttributeFormat for [f32; 4] {
+    const FORMAT: VertexFormat = VertexFormat::Xyzw32Float;
+}
+
+impl VertexAttributeFormat for flink::f32x3 {
+    const FORMAT: VertexFormat = VertexFormat::Xyz32Float;
+}
+
+impl VertexAttributeFormat for flink::f32x4 {
+    const FORMAT: VertexFormat = VertexFormat::Xyzw32Float;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub format: VertexFormat,
+    // bytes from the start of the vertex
+    pub offset: u32,
+}
+
+// vertex data of one buffer binding
+pub trait VertexLayout {
+    const STRIDE: usize;
+    const ATTRIBUTES: &'static [VertexAttribute];
+}
@@ -0,0 +1,10 @@
+#![no_std]
+#![cfg_attr(target_arch = "spirv", feature(register_attr))]
+#![cfg_attr(target_arch = "spirv", register_attr(spirv))]
+
+// the derives refer to `::common`, also from inside this crate
+extern crate self as common;
+
+pub mod layout;
+pub mod locals;
+pub mod random;
@@ -0,0 +1,39 @@
+use flink::{f32x4, f32x4x4};
+
+use crate::layout::{UniformBlock};
+
+// uniform blocks shared by the host and the shaders, bound at binding 0
+
+#[cfg_attr(target_arch = "spirv", spirv(block))]
+#[repr(C)]
+#[derive(Copy, Clone, UniformBlock)]
+pub struct LocalsBackground {
+    pub view_world: f32x4x4,
+    pub proj_view: f32x4x4,
+    pub sky: f32x4,
+}
+
+#[cfg_attr(target_arch = "spirv", spirv(block))]
+#[repr(C)]
+#[derive(Copy, Clone, UniformBlock)]
+pub struct LocalsParticles {
+    pub world_view: f32x4x4,
+    pub view_proj: f32x4x4,
+    pub depth: f32,
+    pub apperture: f32,
+    pub _pad0: f32,
+    pub _pad1: f32,
+}
+
+impl LocalsParticles {
+    pub fn new(world_view: f32x4x4, view_proj: f32x4x4, depth: f32, apperture: f32) -> Self {
+        LocalsParticles {
+            world_view: world_view,
+            view_proj: view_proj,
+            depth: depth,
+            apperture: apperture,
+            _pad0: 0.0,
+            _pad1: 0.0,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// Integer hashing and a small PCG generator, identical results on every platform and
+// on the GPU since no float math library is involved.
+//
+// Per-particle values are `random(seed, index)`, independent values for the same
+// particle come from different streams: `random(stream(seed, 1), index)`.
+
+// PCG-RXS-M-XS 32 bit output permutation applied to one LCG step
+pub fn hash(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+pub fn hash2(a: u32, b: u32) -> u32 {
+    hash(a ^ hash(b))
+}
+
+// seed for an independent sequence derived from `seed`
+pub fn stream(seed: u32, stream: u32) -> u32 {
+    hash2(seed, stream.wrapping_add(0x9e3779b9))
+}
+
+// top 24 bits to a float in [0, 1)
+pub fn to_unit(h: u32) -> f32 {
+    (h >> 8) as f32 * (1.0 / 16777216.0)
+}
+
+// uniform in [0, 1)
+pub fn random(seed: u32, index: u32) -> f32 {
+    to_unit(hash2(seed, index))
+}
+
+// uniform in [-1, 1)
+pub fn random_signed(seed: u32, index: u32) -> f32 {
+    random(seed, index) * 2.0 - 1.0
+}
+
+// sequential generator for code walking through values in order
+#[derive(Debug, Clone, Copy)]
+pub struct Pcg32 {
+    state: u32,
+}
+
+impl Pcg32 {
+    pub fn new(seed: u32) -> Self {
+        Pcg32 { state: hash(seed) }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let out = hash(self.state);
+        self.state = self.state.wrapping_mul(747796405).wrapping_add(2891336453);
+        out
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        to_unit(self.next_u32())
+    }
+}
+
+// process wide seed, set once at startup so runs and screenshots are reproducible
+#[cfg(not(target_arch = "spirv"))]
+mod global {
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    static SEED: AtomicU32 = AtomicU32::new(0);
+
+    pub fn set_seed(seed: u32) {
+        SEED.store(seed, Ordering::Relaxed);
+    }
+
+    pub fn seed() -> u32 {
+        SEED.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(not(target_arch = "spirv"))]
+pub use global::{seed, set_seed};
+
+#[cfg(all(test, not(target_arch = "spirv")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_values() {
+        // pinned, effects and golden images depend on these exact values
+        assert_eq!(hash(0), 0x07bb_2fe2);
+        assert_eq!(hash(1), 0xa8be_ea3c);
+        assert_eq!(hash2(1, 7), 0xa77a_f8df);
+    }
+
+    #[test]
+    fn repeatable() {
+        for index in 0..1000 {
+            assert_eq!(random(42, index), random(42, index));
+            assert_eq!(random_signed(42, index), random_signed(42, index));
+        }
+        let (mut a, mut b) = (Pcg32::new(42), Pcg32::new(42));
+        for _ in 0..1000 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+    }
+
+    #[test]
+    fn seeds_differ() {
+        let same = (0..1000).filter(|&index| random(1, index) == random(2, index)).count();
+        assert!(same < 5, "{} equal values", same);
+        let same = (0..1000).filter(|&index| random(1, index) == random(stream(1, 1), index)).count();
+        assert!(same < 5, "{} equal values across streams", same);
+    }
+
+    #[test]
+    fn unit_range() {
+        assert_eq!(to_unit(0), 0.0);
+        assert!(to_unit(u32::MAX) < 1.0);
+        assert_eq!(random_signed(0, 0), random(0, 0) * 2.0 - 1.0);
+        let mut pcg = Pcg32::new(7);
+        for index in 0..100_000 {
+            let v = random(7, index);
+            assert!(v >= 0.0 && v < 1.0, "random(7, {}) = {}", index, v);
+            let v = random_signed(7, index);
+            assert!(v >= -1.0 && v < 1.0, "random_signed(7, {}) = {}", index, v);
+            let v = pcg.next_f32();
+            assert!(v >= 0.0 && v < 1.0, "next_f32 = {}", v);
+        }
+    }
+
+    #[test]
+    fn global_seed() {
+        let previous = seed();
+        for &value in &[0, 1, 0xdead_beef, u32::MAX] {
+            set_seed(value);
+            assert_eq!(seed(), value);
+        }
+        set_seed(previous);
+    }
+}
@@ -0,0 +1,161 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, Lit, Meta, NestedMeta};
+
+fn has_repr_c(input: &DeriveInput) -> bool {
+    input.attrs.iter().any(|attr| match attr.parse_meta() {
+        Ok(Meta::List(list)) if list.path.is_ident("repr") => list.nested.iter().any(|nested| match nested {
+            NestedMeta::Meta(meta) => meta.path().is_ident("C"),
+            _ => false,
+        }),
+        _ => false,
+    })
+}
+
+// fields of a non generic `repr(C)` struct with named fields
+fn fields(input: &DeriveInput, derive: &str) -> syn::Result<Vec<Field>> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(input.generics.span(), format!("{} does not support generics", derive)));
+    }
+    if !has_repr_c(input) {
+        return Err(syn::Error::new(input.ident.span(), format!("{} needs #[repr(C)]", derive)));
+    }
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields.named.iter().cloned().collect()),
+            _ => Err(syn::Error::new(input.ident.span(), format!("{} needs named fields", derive))),
+        },
+        _ => Err(syn::Error::new(input.ident.span(), format!("{} only supports structs", derive))),
+    }
+}
+
+// `repr(C)` offset of `fields[index]`
+fn host_offset(fields: &[Field], index: usize) -> TokenStream2 {
+    let before = fields[..index].iter().map(|field| &field.ty);
+    let ty = &fields[index].ty;
+    quote! {{
+        let offset = 0usize;
+        #(let offset = ::common::layout::align_up(offset, ::core::mem::align_of::<#before>()) + ::core::mem::size_of::<#before>();)*
+        ::common::layout::align_up(offset, ::core::mem::align_of::<#ty>())
+    }}
+}
+
+// std140 offset of a field of type `ty` following fields of type `before`
+fn std140_offset(before: &[&syn::Type], ty: &syn::Type) -> TokenStream2 {
+    quote! {{
+        let std140 = 0usize;
+        #(let std140 = ::common::layout::align_up(std140, <#before as ::common::layout::Std140>::ALIGN) + <#before as ::common::layout::Std140>::SIZE;)*
+        ::common::layout::align_up(std140, <#ty as ::common::layout::Std140>::ALIGN)
+    }}
+}
+
+// Implements `Std140` and `UniformBlock` and checks at compile time that every field sits at
+// its std140 offset and the struct is padded to 16 bytes. Padding has to be explicit fields
+// since the struct is shared with the shader, a failed check points at the misplaced field.
+#[proc_macro_derive(UniformBlock)]
+pub fn derive_uniform_block(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let fields = match fields(&input, "UniformBlock") {
+        Ok(fields) => fields,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let name = &input.ident;
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+
+    let checks = fields.iter().enumerate().map(|(i, field)| {
+        let host = host_offset(&fields, i);
+        let std140 = std140_offset(&types[..i], &field.ty);
+        quote_spanned! {field.span()=>
+            let _ = [(); 1][(#std140 != #host) as usize];
+        }
+    });
+
+    let block_fields = fields.iter().enumerate().map(|(i, field)| {
+        let name = field.ident.as_ref().map(|ident| ident.to_string()).unwrap_or_default();
+        let offset = std140_offset(&types[..i], &field.ty);
+        quote! {
+            ::common::layout::BlockField { name: #name, offset: #offset }
+        }
+    });
+
+    let expanded = quote! {
+        impl ::common::layout::Std140 for #name {
+            const ALIGN: usize = 16;
+            const SIZE: usize = {
+                let std140 = 0usize;
+                #(let std140 = ::common::layout::align_up(std140, <#types as ::common::layout::Std140>::ALIGN) + <#types as ::common::layout::Std140>::SIZE;)*
+                ::common::layout::align_up(std140, 16)
+            };
+        }
+
+        impl ::common::layout::UniformBlock for #name {
+            const FIELDS: &'static [::common::layout::BlockField] = &[#(#block_fields),*];
+        }
+
+        const _: () = {
+            #(#checks)*
+            let _ = [(); 1][(::core::mem::size_of::<#name>() != <#name as ::common::layout::Std140>::SIZE) as usize];
+        };
+    };
+    expanded.into()
+}
+
+// `#[vertex(location = N)]` of a field
+fn location(field: &Field) -> syn::Result<u32> {
+    for attr in &field.attrs {
+        if !attr.path.is_ident("vertex") {
+            continue;
+        }
+        if let Meta::List(list) = attr.parse_meta()? {
+            for nested in list.nested.iter() {
+                if let NestedMeta::Meta(Meta::NameValue(value)) = nested {
+                    if let (true, Lit::Int(int)) = (value.path.is_ident("location"), &value.lit) {
+                        return int.base10_parse();
+                    }
+                }
+            }
+        }
+    }
+    Err(syn::Error::new(field.span(), "missing #[vertex(location = N)]"))
+}
+
+// Implements `VertexLayout`, one attribute per field at its `repr(C)` offset, the format
+// comes from the field type through `VertexAttributeFormat`.
+#[proc_macro_derive(VertexLayout, attributes(vertex))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let fields = match fields(&input, "VertexLayout") {
+        Ok(fields) => fields,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let name = &input.ident;
+
+    let mut attributes = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let location = match location(field) {
+            Ok(location) => location,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        let ty = &field.ty;
+        let offset = host_offset(&fields, i);
+        attributes.push(quote! {
+            ::common::layout::VertexAttribute {
+                location: #location,
+                format: <#ty as ::common::layout::VertexAttributeFormat>::FORMAT,
+                offset: #offset as u32,
+            }
+        });
+    }
+
+    let expanded = quote! {
+        impl ::common::layout::VertexLayout for #name {
+            const STRIDE: usize = ::core::mem::size_of::<#name>();
+            const ATTRIBUTES: &'static [::common::layout::VertexAttribute] = &[#(#attributes),*];
+        }
+    };
+    expanded.into()
+}
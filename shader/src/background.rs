@@ -0,0 +1,103 @@
+#[cfg(target_arch = "spirv")]
+use spirv_std::storage_class::{Input, Output, Uniform};
+use flink::{f32x3, f32x4, vec3, vec4};
+
+use crate::math::saturate;
+use crate::LocalsBackground;
+
+pub const VS: &str = "background_vs";
+
+// sky look, one fragment entry point each
+pub struct Sky {
+    pub name: &'static str,
+    pub entry_point: &'static str,
+}
+
+// the first one is the default
+pub const SKIES: &[Sky] = &[
+    Sky { name: "tinted", entry_point: "background_fs" },
+    Sky { name: "blues", entry_point: "background_blues_fs" },
+    Sky { name: "green", entry_point: "background_green_fs" },
+];
+
+// world space direction through a clip space position on the near plane
+pub fn view_dir(locals: LocalsBackground, position_clip: f32x4) -> f32x3 {
+    let position_view = position_clip * locals.proj_view;
+    let position_world = position_view * locals.view_world;
+    return vec3(position_world.x, position_world.y, position_world.z);
+}
+
+// very dark, tinted by the scene (purple by default)
+pub fn tinted(locals: LocalsBackground, view_dir: f32x3) -> f32x4 {
+    let v = saturate(view_dir.x - 0.5 * 0.5 + view_dir.y * 0.5 - view_dir.z * 0.3) * 0.02 + 0.015;
+    return vec4(v * locals.sky.x, v * locals.sky.y, v * locals.sky.z, 1.0);
+}
+
+// colorful blues
+pub fn blues(view_dir: f32x3) -> f32x4 {
+    return vec4((0.1 + 0.3 * view_dir.x) * 0.2, (0.3 + 0.3 * view_dir.y) * 0.2, (0.5 + 0.3 * view_dir.z) * 0.2, 1.0);
+}
+
+// very dark green
+pub fn green(view_dir: f32x3) -> f32x4 {
+    let v = saturate(view_dir.x - 0.5 * 0.5 + view_dir.y * 0.5 - view_dir.z * 0.3) * 0.02 + 0.015;
+    return vec4(v * 0.2, v, v, 1.0);
+}
+
+// color of the sky at `index` in `SKIES`
+pub fn color(index: usize, locals: LocalsBackground, view_dir: f32x3) -> f32x4 {
+    match index {
+        1 => blues(view_dir),
+        2 => green(view_dir),
+        _ => tinted(locals, view_dir),
+    }
+}
+
+#[cfg(target_arch = "spirv")]
+#[allow(unused_attributes)]
+#[spirv(vertex)]
+pub fn background_vs(
+    #[spirv(vertex_id)] vert_id: Input<i32>,
+    #[spirv(binding = 0)] u_locals: Uniform<LocalsBackground>,
+    #[spirv(position)] mut a_position: Output<f32x4>,
+    #[spirv(location = 0)] mut a_view_dir: Output<f32x3>,
+) {
+    let u_locals = u_locals.load();
+
+    let position_uv = flink::geometry::Fullscreen::position(vert_id.load());
+    let position_clip = vec4(position_uv.x, position_uv.y, 0.0, 1.0);
+
+    a_view_dir.store(view_dir(u_locals, position_clip));
+    a_position.store(position_clip);
+}
+
+#[cfg(target_arch = "spirv")]
+#[allow(unused_attributes)]
+#[spirv(fragment)]
+pub fn background_fs(
+    #[spirv(binding = 0)] u_locals: Uniform<LocalsBackground>,
+    #[spirv(location = 0)] f_view_dir: Input<f32x3>,
+    mut output: Output<f32x4>,
+) {
+    output.store(tinted(u_locals.load(), f_view_dir.load()));
+}
+
+#[cfg(target_arch = "spirv")]
+#[allow(unused_attributes)]
+#[spirv(fragment)]
+pub fn background_blues_fs(
+    #[spirv(location = 0)] f_view_dir: Input<f32x3>,
+    mut output: Output<f32x4>,
+) {
+    output.store(blues(f_view_dir.load()));
+}
+
+#[cfg(target_arch = "spirv")]
+#[allow(unused_attributes)]
+#[spirv(fragment)]
+pub fn background_green_fs(
+    #[spirv(location = 0)] f_view_dir: Input<f32x3>,
+    mut output: Output<f32x4>,
+) {
+    output.store(green(f_view_dir.load()));
+}
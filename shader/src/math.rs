@@ -0,0 +1,64 @@
+// scalar helpers shared by the shaders and the host
+pub fn abs(v: f32) -> f32 {
+    if v < 0.0 { return 0.0 - v; }
+    return v;
+}
+
+pub fn clamp(v: f32, a: f32, b: f32) -> f32 {
+    if v < a { return a; }
+    if v > b { return b; }
+    return v;
+}
+
+pub fn saturate(v: f32) -> f32 {
+    return clamp(v, 0.0, 1.0);
+}
+
+pub fn lerp(a: f32, b: f32, v: f32) -> f32 {
+    return a + (b - a) * v;
+}
+
+// hermite step from 0 at `a` to 1 at `b`
+pub fn smoothstep(a: f32, b: f32, v: f32) -> f32 {
+    let t = saturate((v - a) / (b - a));
+    return t * t * (3.0 - 2.0 * t);
+}
+
+#[cfg(all(test, not(target_arch = "spirv")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_range() {
+        assert_eq!(clamp(-2.0, -1.0, 1.0), -1.0);
+        assert_eq!(clamp(2.0, -1.0, 1.0), 1.0);
+        assert_eq!(clamp(0.25, -1.0, 1.0), 0.25);
+        assert_eq!(abs(-0.5), 0.5);
+        assert_eq!(abs(0.5), 0.5);
+    }
+
+    #[test]
+    fn saturate_range() {
+        assert_eq!(saturate(-0.1), 0.0);
+        assert_eq!(saturate(1.1), 1.0);
+        assert_eq!(saturate(0.5), 0.5);
+    }
+
+    #[test]
+    fn lerp_ends() {
+        assert_eq!(lerp(2.0, 4.0, 0.0), 2.0);
+        assert_eq!(lerp(2.0, 4.0, 1.0), 4.0);
+        assert_eq!(lerp(2.0, 4.0, 0.5), 3.0);
+        assert_eq!(lerp(2.0, 4.0, 2.0), 6.0);
+    }
+
+    #[test]
+    fn smoothstep_shape() {
+        assert_eq!(smoothstep(1.0, 3.0, 0.0), 0.0);
+        assert_eq!(smoothstep(1.0, 3.0, 1.0), 0.0);
+        assert_eq!(smoothstep(1.0, 3.0, 2.0), 0.5);
+        assert_eq!(smoothstep(1.0, 3.0, 3.0), 1.0);
+        assert_eq!(smoothstep(1.0, 3.0, 4.0), 1.0);
+        assert_eq!(smoothstep(0.0, 1.0, 0.25), 0.15625);
+    }
+}
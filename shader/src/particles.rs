@@ -0,0 +1,178 @@
+#[cfg(target_arch = "spirv")]
+use spirv_std::storage_class::{Input, Output, Uniform, UniformConstant};
+#[cfg(target_arch = "spirv")]
+use spirv_std::{SampledImage, Image2d};
+use flink::{f32x3, f32x4, vec3, vec4};
+
+use crate::math::{abs, lerp};
+use crate::LocalsParticles;
+
+pub const VS: &str = "particles_vs";
+pub const FS: &str = "particles_fs";
+
+#[derive(Copy, Clone)]
+pub struct ParticleVertex {
+    pub position: f32x4,
+    // quad uv, with the dof alpha in z
+    pub texcoord: f32x3,
+}
+
+// view space billboard scale and alpha for a particle at view depth `z`
+pub fn dof(locals: LocalsParticles, z: f32, size: f32) -> (f32, f32) {
+    let scale = size * (abs(locals.depth - z) * lerp(0.0, 10.0, locals.apperture) + 1.0);
+    //let scale = size * (abs(locals.depth - z) * 0.01 + 1.0);
+    let alpha = 1.0 - abs(locals.depth - z) * lerp(0.0, 1.0, locals.apperture);
+    return (scale, alpha);
+}
+
+// one corner `position` of the quad for the particle at `posscale` (position and size),
+// particles at y == 0 lie flat on the ground (shadows), the others face the camera
+pub fn vertex(locals: LocalsParticles, position: f32x3, posscale: f32x4) -> ParticleVertex {
+    let mut alpha = 1.0;
+
+    let position_clip = if posscale.y > 0.0 || posscale.y < 0.0 {
+        // billboard
+        let mut pos_view = vec4(posscale.x, posscale.y, posscale.z, 1.0) * locals.world_view;
+        let (scale, dof_alpha) = dof(locals, pos_view.z, posscale.w);
+        alpha = dof_alpha;
+
+        pos_view.x += position.x * scale;
+        pos_view.y += position.y * scale;
+        pos_view.z += position.z * scale;
+
+        pos_view * locals.view_proj
+    } else {
+        // horizontal, flag
+        let scale = posscale.w;
+        let pos_view = vec4(posscale.x + position.x * scale, posscale.y + position.z * scale, posscale.z + position.y * scale, 1.0) * locals.world_view;
+
+        pos_view * locals.view_proj
+    };
+
+    return ParticleVertex {
+        position: position_clip,
+        texcoord: vec3(position.x + 0.5, 0.5 - position.y, alpha),
+    };
+}
+
+// premultiplied by the alpha it returns, blended with `SrcAlpha, One`
+pub fn color(tex: f32x4, texcoord: f32x3, color: f32x4) -> f32x4 {
+    // nice axis coloured (RGB), needs the particle position
+    //color.x = color.w * posscale.x * 0.3;
+    //color.y = color.w * posscale.y * 0.3;
+    //color.z = color.w * posscale.z * 0.3;
+
+    return vec4(tex.x * color.x, tex.y * color.y, tex.z * color.z, 1.0) * texcoord.z * tex.w * color.w;
+}
+
+#[cfg(target_arch = "spirv")]
+#[allow(unused_attributes)]
+#[spirv(vertex)]
+pub fn particles_vs(
+    #[spirv(vertex_id)] _c_vertex_id: Input<i32>,
+    #[spirv(instance_id)] _c_instance_id: Input<i32>,
+    #[spirv(binding = 0)] u_locals: Uniform<LocalsParticles>,
+    #[spirv(location = 0)] v_position: Input<f32x3>,
+    #[spirv(location = 1)] v_posscale: Input<f32x4>,
+    #[spirv(location = 2)] v_color: Input<f32x4>,
+    #[spirv(position)] mut a_position: Output<f32x4>,
+    #[spirv(location = 0)] mut a_texcoord: Output<f32x3>,
+    #[spirv(location = 1)] mut a_color: Output<f32x4>,
+) {
+    let vertex = vertex(u_locals.load(), v_position.load(), v_posscale.load());
+
+    a_position.store(vertex.position);
+    a_texcoord.store(vertex.texcoord);
+    a_color.store(v_color.load());
+}
+
+#[cfg(target_arch = "spirv")]
+#[allow(unused_attributes)]
+#[spirv(fragment)]
+pub fn particles_fs(
+    #[spirv(binding = 0)] u_texture: UniformConstant<SampledImage<Image2d>>,
+    #[spirv(location = 0)] f_texcoord: Input<f32x3>,
+    #[spirv(location = 1)] f_color: Input<f32x4>,
+    mut output: Output<f32x4>,
+) {
+    let texture = u_texture.load();
+    let texcoord = f_texcoord.load();
+    let tex = texture.sample(spirv_std::glam::Vec2::new(texcoord.x, texcoord.y));
+
+    output.store(color(vec4(tex.x, tex.y, tex.z, tex.w), texcoord, f_color.load()));
+}
+
+#[cfg(all(test, not(target_arch = "spirv")))]
+mod tests {
+    use super::*;
+    use flink::f32x4x4;
+
+    const DEPTH: f32 = -4.0;
+
+    fn locals(apperture: f32) -> LocalsParticles {
+        LocalsParticles::new(
+            f32x4x4::look_at_inv(vec3(0.0, 0.0, 4.0), vec3(0.0, 0.0, 1.0)),
+            f32x4x4::perspective(1.0, 1.5, 0.1, 100.0),
+            DEPTH,
+            apperture,
+        )
+    }
+
+    #[test]
+    fn dof_in_focus() {
+        assert_eq!(dof(locals(0.5), DEPTH, 0.1), (0.1, 1.0));
+        // no apperture, everything is sharp
+        assert_eq!(dof(locals(0.0), DEPTH + 3.0, 0.1), (0.1, 1.0));
+    }
+
+    #[test]
+    fn dof_out_of_focus() {
+        let (scale, alpha) = dof(locals(0.5), DEPTH + 2.0, 0.25);
+        assert_eq!(scale, 0.25 * 11.0);
+        assert_eq!(alpha, 0.0);
+        assert_eq!(dof(locals(0.5), DEPTH - 2.0, 0.25), (scale, alpha));
+    }
+
+    #[test]
+    fn billboard_vertex() {
+        let locals = locals(0.1);
+        let posscale = vec4(0.5, 1.0, -0.25, 0.2);
+        let corner = vertex(locals, vec3(-0.5, 0.5, 0.0), posscale);
+        let z = (vec4(posscale.x, posscale.y, posscale.z, 1.0) * locals.world_view).z;
+        assert_eq!(corner.texcoord.x, 0.0);
+        assert_eq!(corner.texcoord.y, 0.0);
+        assert_eq!(corner.texcoord.z, dof(locals, z, posscale.w).1);
+
+        // corners face the camera, opposite ones are a view space `scale` apart in x and y
+        let opposite = vertex(locals, vec3(0.5, -0.5, 0.0), posscale);
+        assert_eq!(opposite.texcoord.x, 1.0);
+        assert_eq!(opposite.texcoord.y, 1.0);
+        assert_eq!(opposite.texcoord.z, corner.texcoord.z);
+        assert!(opposite.position.x / opposite.position.w > corner.position.x / corner.position.w);
+        assert!(opposite.position.y / opposite.position.w < corner.position.y / corner.position.w);
+    }
+
+    #[test]
+    fn shadow_vertex() {
+        let locals = locals(0.1);
+        let posscale = vec4(0.5, 0.0, -0.25, 2.0);
+        let position = vec3(0.5, -0.5, 0.0);
+        let shadow = vertex(locals, position, posscale);
+        // flat on the ground, never faded by the dof
+        let ground = vec4(0.5 + 0.5 * 2.0, 0.0, -0.25 - 0.5 * 2.0, 1.0) * locals.world_view * locals.view_proj;
+        assert_eq!(shadow.texcoord.z, 1.0);
+        assert_eq!(shadow.position.x, ground.x);
+        assert_eq!(shadow.position.y, ground.y);
+        assert_eq!(shadow.position.z, ground.z);
+        assert_eq!(shadow.position.w, ground.w);
+    }
+
+    #[test]
+    fn color_premultiplied() {
+        let out = color(vec4(1.0, 0.5, 0.25, 0.5), vec3(0.0, 0.0, 0.5), vec4(1.0, 1.0, 1.0, 1.0));
+        assert_eq!((out.x, out.y, out.z, out.w), (0.25, 0.125, 0.0625, 0.25));
+
+        let out = color(vec4(1.0, 1.0, 1.0, 1.0), vec3(0.0, 0.0, 1.0), vec4(0.5, 2.0, 1.0, 0.0));
+        assert_eq!((out.x, out.y, out.z, out.w), (0.0, 0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,18 @@
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+
+// transfer functions of an sRGB framebuffer, for passes writing to a linear target
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        return c / 12.92;
+    }
+    return ((c + 0.055) / 1.055).powf(2.4);
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * c.powf(1.0 / 2.4) - 0.055;
+}
@@ -1,56 +1,77 @@
-use anyhow::{Result};
-use flink::{f32x4x4};
+use anyhow::{anyhow, Result};
+use common::layout::{Std140, UniformBlock};
+use common::locals::{LocalsBackground};
+use flink::{vec4};
 
 use crate::input;
 use crate::camera;
+use crate::layout;
+use crate::spirv;
 
-#[repr(C)]
-struct LocalsBackground {
-    world_view: f32x4x4,
-    view_proj: f32x4x4,
+// very dark purple
+pub const DEFAULT_SKY: [f32; 3] = [1.0, 0.2, 1.0];
+
+const UNIFORMS: &[layout::UniformDesc] = &[layout::UniformDesc {
+    binding: 0,
+    size: <LocalsBackground as Std140>::SIZE,
+    fields: <LocalsBackground as UniformBlock>::FIELDS,
+}];
+
+// one pipeline per `shader::background::SKIES` entry, they only differ in the fragment shader
+pub fn pipelines() -> Vec<layout::PipelineDesc> {
+    shader::background::SKIES
+        .iter()
+        .map(|sky| layout::PipelineDesc {
+            name: "background",
+            vertex: shader::background::VS,
+            fragment: sky.entry_point,
+            // fullscreen triangle from the vertex id
+            vertex_buffers: &[],
+            uniforms: UNIFORMS,
+            samplers: &[],
+        })
+        .collect()
+}
+
+// index of the sky variant `name`
+pub fn find_variant(name: &str) -> Result<usize> {
+    let skies = shader::background::SKIES;
+    skies.iter().position(|sky| sky.name == name).ok_or_else(|| {
+        let names: Vec<&str> = skies.iter().map(|sky| sky.name).collect();
+        anyhow!("unknown sky `{}`, expected one of: {}", name, names.join(", "))
+    })
 }
 
 pub struct Background {
-    pipeline: grr::Pipeline,
+    pipelines: Vec<grr::Pipeline>,
     vertex_array: grr::VertexArray,
     sampler: grr::Sampler,
+    sky: [f32; 3],
+    variant: usize,
+}
+
+// one pipeline per sky variant from `spirv`, none are left behind on failure
+pub unsafe fn create_pipelines(grr: &grr::Device, spirv: &[u8]) -> Result<Vec<grr::Pipeline>> {
+    let mut created = Vec::new();
+    for desc in pipelines() {
+        match layout::create_pipeline(grr, &desc, spirv) {
+            Ok(pipeline) => created.push(pipeline),
+            Err(err) => {
+                for pipeline in created {
+                    grr.delete_pipeline(pipeline);
+                }
+                return Err(err);
+            }
+        }
+    }
+    Ok(created)
 }
 
 impl Background {
     pub fn new(grr: &grr::Device) -> Result<Self> {
         unsafe {
-            let spirv = include_bytes!(env!("shader.spv"));
-
             let vertex_array = grr.create_vertex_array(&[])?;
-
-            let vs = grr.create_shader(
-                grr::ShaderStage::Vertex,
-                grr::ShaderSource::Spirv {
-                    entrypoint: "background_vs",
-                },
-                &spirv[..],
-                grr::ShaderFlags::VERBOSE,
-            ).unwrap();
-            
-            let fs = grr.create_shader(
-                grr::ShaderStage::Fragment,
-                grr::ShaderSource::Spirv {
-                    entrypoint: "background_fs",
-                },
-                &spirv[..],
-                grr::ShaderFlags::VERBOSE,
-            ).unwrap();
-
-            let pipeline = grr.create_graphics_pipeline(
-                grr::VertexPipelineDesc {
-                    vertex_shader: vs,
-                    tessellation_control_shader: None,
-                    tessellation_evaluation_shader: None,
-                    geometry_shader: None,
-                    fragment_shader: Some(fs),
-                },
-                grr::PipelineFlags::VERBOSE,
-            )?;
+            let pipelines = create_pipelines(grr, spirv::SHADER)?;
 
             let sampler = grr.create_sampler(grr::SamplerDesc {
                 min_filter: grr::Filter::Linear,
@@ -68,13 +89,35 @@ impl Background {
             }).unwrap();
 
             Ok(Background {
-                pipeline: pipeline,
+                pipelines: pipelines,
                 vertex_array: vertex_array,
                 sampler: sampler,
+                sky: DEFAULT_SKY,
+                variant: 0,
             })
         }
     }
 
+    // swaps in the pipelines of a rebuilt shader module from `create_pipelines` and
+    // deletes the running ones
+    pub fn replace_pipelines(&mut self, grr: &grr::Device, pipelines: Vec<grr::Pipeline>) {
+        unsafe {
+            for pipeline in std::mem::replace(&mut self.pipelines, pipelines) {
+                grr.delete_pipeline(pipeline);
+            }
+        }
+    }
+
+    // tint of the default "tinted" sky, [0.2, 1.0, 1.0] gives the dark green
+    pub fn set_sky(&mut self, sky: [f32; 3]) {
+        self.sky = sky;
+    }
+
+    // index into `shader::background::SKIES`, see `find_variant`
+    pub fn set_variant(&mut self, variant: usize) {
+        self.variant = variant.min(self.pipelines.len() - 1);
+    }
+
     pub fn update(&mut self, grr: &grr::Device, camera: &camera::Camera, _input: &input::Input, _time: f32) {
         unsafe {
             let color_blend = grr::ColorBlend {
@@ -103,8 +146,9 @@ impl Background {
             };
 
             let locals = LocalsBackground {
-                world_view: camera.world_view(),
-                view_proj: camera.view_proj_inv(),
+                view_world: camera.world_view(),
+                proj_view: camera.view_proj_inv(),
+                sky: vec4(self.sky[0], self.sky[1], self.sky[2], 1.0),
             };
             let u_locals = grr
                 .create_buffer_from_host(
@@ -113,17 +157,13 @@ impl Background {
                 )
                 .unwrap();
 
-            grr.bind_pipeline(self.pipeline);
+            grr.bind_pipeline(self.pipelines[self.variant]);
             grr.bind_depth_stencil_state(&state_ds);
             grr.bind_color_blend_state(&color_blend);
             grr.bind_vertex_array(self.vertex_array);
             grr.bind_uniform_buffers(
                 0,
-                &[grr::BufferRange {
-                    buffer: u_locals,
-                    offset: 0,
-                    size: std::mem::size_of::<LocalsBackground>() as _,
-                }],
+                &[layout::uniform_range::<LocalsBackground>(u_locals)],
             );
             grr.bind_samplers(0, &[self.sampler]);
             grr.draw(grr::Primitive::Triangles, 0..3, 0..1);
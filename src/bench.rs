@@ -0,0 +1,75 @@
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crate::effect;
+use crate::raster;
+
+// CPU time of the per-frame particle update, on the calling thread and across the rayon
+// pool. Writes into host buffers so it runs without a GPU, the window maps the GL buffers
+// and writes the same way.
+
+// simulated frame rate the benchmark frames are spaced at
+const FPS: f32 = 60.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    pub mean: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Timing {
+    fn from_samples(samples: &[Duration]) -> Self {
+        let total: Duration = samples.iter().sum();
+        Timing {
+            mean: total / samples.len().max(1) as u32,
+            min: samples.iter().min().copied().unwrap_or_default(),
+            max: samples.iter().max().copied().unwrap_or_default(),
+        }
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// `frames` updates of `effect` after one untimed warm up, with the threading `parallel` picks
+pub fn time_updates(effect: &dyn effect::Effect, frames: u32, parallel: bool) -> Timing {
+    let mut buffers = raster::ParticleBuffers::new(effect.capacity());
+    let mut update = |time: f32| {
+        buffers.write(|particles| {
+            particles.set_parallel(parallel);
+            effect.update(particles, time);
+        })
+    };
+    update(0.0);
+    let samples: Vec<Duration> = (0..frames)
+        .map(|frame| {
+            let start = Instant::now();
+            update(frame as f32 / FPS);
+            start.elapsed()
+        })
+        .collect();
+
+    Timing::from_samples(&samples)
+}
+
+// prints the per-frame update time of every effect, single threaded and in parallel
+pub fn run(settings: &effect::Settings, effects: &[&str], frames: u32) -> Result<()> {
+    println!("{} frames, {} threads", frames, rayon::current_num_threads());
+    println!("{:<10} {:>10} {:>22} {:>22} {:>8}", "effect", "particles", "serial ms (min/max)", "parallel ms (min/max)", "speedup");
+    for name in effects {
+        let effect = effect::create(name, settings)?;
+        let serial = time_updates(effect.as_ref(), frames, false);
+        let parallel = time_updates(effect.as_ref(), frames, true);
+        println!(
+            "{:<10} {:>10} {:>22} {:>22} {:>7.1}x",
+            name,
+            effect.capacity(),
+            format!("{:.2} ({:.2}/{:.2})", ms(serial.mean), ms(serial.min), ms(serial.max)),
+            format!("{:.2} ({:.2}/{:.2})", ms(parallel.mean), ms(parallel.min), ms(parallel.max)),
+            ms(serial.mean) / ms(parallel.mean).max(1e-6),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anyhow::{bail, Context, Result};
+use rust_snippets::{reflect, spirv};
+
+// Lists the interface of the shader entry points and checks it against the host pipelines,
+// needs no GPU. Reads `spirv::SHADER` built with the crate unless given a path.
+fn main() -> Result<()> {
+    let spirv = match std::env::args_os().nth(1) {
+        Some(path) => std::fs::read(&path).with_context(|| format!("failed to read {}", path.to_string_lossy()))?,
+        None => spirv::SHADER.to_vec(),
+    };
+    let module = reflect::Module::parse(&spirv)?;
+    print!("{}", module);
+    println!();
+
+    let pipelines = reflect::host_pipelines();
+    let mut failed = 0;
+    for desc in &pipelines {
+        let errors = reflect::validate(&module, desc);
+        if errors.is_empty() {
+            println!("{:<12} {:<20} ok", desc.name, desc.fragment);
+        } else {
+            println!("{:<12} {:<20} FAILED", desc.name, desc.fragment);
+            for error in &errors {
+                println!("  {}", error);
+            }
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        bail!("{} of {} pipelines don't match the shaders", failed, pipelines.len());
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use flink::{f32x4x4, vec3, Vec3};
+use flink::{f32x4x4, vec3, Vec2, Vec3};
 
 use crate::input;
 
@@ -35,26 +35,16 @@ impl Camera {
         }
     }
 
-    pub fn update(&mut self, grr: &grr::Device, input: &input::Input, width: f32, height: f32, _time: f32) {
-        let aspect = width / height;
-        self.position.x = (input.mouse_pos().x / width + 0.5) * 10.0;
-        self.position.y = (input.mouse_pos().y / height + 0.5) * 2.0;
-        self.position.z = self.distance;
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+    }
 
-        self.world_view = f32x4x4::look_at(self.position, self.position);
-        self.view_proj = f32x4x4::perspective(
-            std::f32::consts::PI * self.fov,
-            aspect,
-            0.1,
-            10000.0,
-        );
-        self.world_view_inv = f32x4x4::look_at_inv(self.position, self.position);
-        self.view_proj_inv = f32x4x4::perspective_inv(
-            std::f32::consts::PI * self.fov,
-            aspect,
-            0.1,
-            10000.0,
-        );        
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance;
+    }
+
+    pub fn update(&mut self, grr: &grr::Device, input: &input::Input, width: f32, height: f32, _time: f32) {
+        self.update_view(input.mouse_pos(), width, height);
 
         unsafe {
             grr.set_viewport(
@@ -80,6 +70,29 @@ impl Camera {
         }
     }
 
+    // matrices for a mouse position and viewport size, without touching the device
+    pub fn update_view(&mut self, mouse_pos: Vec2<f32>, width: f32, height: f32) {
+        let aspect = width / height;
+        self.position.x = (mouse_pos.x / width + 0.5) * 10.0;
+        self.position.y = (mouse_pos.y / height + 0.5) * 2.0;
+        self.position.z = self.distance;
+
+        self.world_view = f32x4x4::look_at(self.position, self.position);
+        self.view_proj = f32x4x4::perspective(
+            std::f32::consts::PI * self.fov,
+            aspect,
+            0.1,
+            10000.0,
+        );
+        self.world_view_inv = f32x4x4::look_at_inv(self.position, self.position);
+        self.view_proj_inv = f32x4x4::perspective_inv(
+            std::f32::consts::PI * self.fov,
+            aspect,
+            0.1,
+            10000.0,
+        );        
+    }
+
     pub fn world_view(&self) -> f32x4x4 {
         self.world_view
     }
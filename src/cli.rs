@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+use rust_snippets::{golden, palette};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+fn parse_size(s: &str) -> Result<Size> {
+    let mut parts = s.splitn(2, |c| c == 'x' || c == 'X');
+    let width = parts.next().unwrap_or("").trim().parse::<u32>();
+    let height = parts.next().unwrap_or("").trim().parse::<u32>();
+    match (width, height) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok(Size { width: width, height: height }),
+        _ => Err(anyhow!("expected <width>x<height>, e.g. 1920x1080")),
+    }
+}
+
+fn parse_msaa(s: &str) -> Result<u16> {
+    let samples = s.parse::<u16>()?;
+    if samples != 0 && !samples.is_power_of_two() {
+        return Err(anyhow!("sample count must be 0 or a power of two"));
+    }
+    Ok(samples)
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "rust-snippets", about = "Framework test bed using grr and rust-gpu")]
+pub struct Options {
+    /// Effect to run, see `list-effects`
+    #[structopt(short, long)]
+    pub effect: Option<String>,
+
+    /// Scene script (TOML) sequencing effects, palettes and camera over time
+    #[structopt(long, parse(from_os_str))]
+    pub timeline: Option<PathBuf>,
+
+    /// Window size in logical pixels
+    #[structopt(long, default_value = "1024x768", parse(try_from_str = parse_size))]
+    pub size: Size,
+
+    /// Number of MSAA samples, 0 disables multisampling
+    #[structopt(long, default_value = "4", parse(try_from_str = parse_msaa))]
+    pub msaa: u16,
+
+    /// Disable the sRGB framebuffer
+    #[structopt(long)]
+    pub no_srgb: bool,
+
+    /// Camera field of view, as a fraction of PI
+    #[structopt(long, default_value = "0.40")]
+    pub fov: f32,
+
+    /// Camera distance to the origin
+    #[structopt(long, default_value = "5.0")]
+    pub distance: f32,
+
+    /// Borderless fullscreen on the primary monitor
+    #[structopt(long)]
+    pub fullscreen: bool,
+
+    /// Background look, one of `tinted` (uses the scene tint), `blues` or `green`
+    #[structopt(long, default_value = "tinted")]
+    pub sky: String,
+
+    /// Rebuild the shaders when their sources change and swap the pipelines, needs a build
+    /// with `--features hot-reload`
+    #[structopt(long)]
+    pub hot_reload: bool,
+
+    /// Simulation speed relative to the wall clock, `[` and `]` halve and double it
+    #[structopt(long, default_value = "1.0")]
+    pub time_scale: f32,
+
+    /// Advance the simulation by 1/fps per frame regardless of the wall clock
+    #[structopt(long)]
+    pub fixed_fps: Option<f32>,
+
+    /// Simulation time in seconds the window starts at, e.g. the start of a timeline scene
+    #[structopt(long, default_value = "0.0")]
+    pub start_time: f32,
+
+    /// Crossfade duration in seconds when switching effects
+    #[structopt(long, default_value = "1.0")]
+    pub crossfade: f32,
+
+    /// Palette library (TOML)
+    #[structopt(long, default_value = palette::DEFAULT_PATH, parse(from_os_str))]
+    pub palettes: PathBuf,
+
+    /// Palette replacing an effect's default, `<name>` for the starting effect or
+    /// `<effect>=<name>`, may be repeated
+    #[structopt(long, number_of_values = 1)]
+    pub palette: Vec<String>,
+
+    /// Seed for per-particle variation, the same seed gives the same frames
+    #[structopt(long, default_value = "0")]
+    pub seed: u32,
+
+    /// Particle count replacing each effect's default, rounded to fit the effect's layout
+    #[structopt(long)]
+    pub particles: Option<u32>,
+
+    /// Update the particles on the main thread only instead of across all cores
+    #[structopt(long)]
+    pub single_threaded: bool,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// List the available effects
+    ListEffects,
+    /// List the palettes in the palette library
+    ListPalettes,
+    /// Render one frame with the software rasterizer, no GPU needed
+    Preview {
+        /// Effect time in seconds
+        #[structopt(long, default_value = "0.0")]
+        time: f32,
+
+        /// Output image
+        #[structopt(short, long, default_value = "preview.png", parse(from_os_str))]
+        output: PathBuf,
+
+        /// Render with the GL pipeline in a headless context instead of the software
+        /// rasterizer, works with Mesa's llvmpipe and no display
+        #[structopt(long)]
+        gpu: bool,
+    },
+    /// Render an image sequence at a fixed frame rate, `frame-00000.png` onwards
+    Render {
+        /// Effect time of the first frame in seconds
+        #[structopt(long, default_value = "0.0")]
+        start: f32,
+
+        /// Effect time in seconds the sequence stops before
+        #[structopt(long, default_value = "10.0")]
+        end: f32,
+
+        /// Frames per second
+        #[structopt(long, default_value = "60")]
+        fps: f32,
+
+        /// Frame size, `--size` if not given
+        #[structopt(long, parse(try_from_str = parse_size))]
+        resolution: Option<Size>,
+
+        /// Output directory
+        #[structopt(short, long, default_value = "render", parse(from_os_str))]
+        output: PathBuf,
+
+        /// Render with the GL pipeline in a headless context instead of the software
+        /// rasterizer
+        #[structopt(long)]
+        gpu: bool,
+    },
+    /// Time the CPU particle update of every effect, or of `--effect`, single threaded and in
+    /// parallel
+    Bench {
+        /// Frames to time per effect and mode
+        #[structopt(long, default_value = "120")]
+        frames: u32,
+    },
+    /// Compare every effect against the golden images, rendered with the software rasterizer
+    Check {
+        /// Replace the golden images with the current output
+        #[structopt(long)]
+        bless: bool,
+
+        /// Directory holding the golden images
+        #[structopt(long, default_value = golden::DEFAULT_DIR, parse(from_os_str))]
+        golden: PathBuf,
+
+        /// Directory for the output and diff images of failed cases
+        #[structopt(short, long, default_value = golden::DEFAULT_OUTPUT, parse(from_os_str))]
+        output: PathBuf,
+
+        /// Largest root mean square channel difference, in [0, 1]
+        #[structopt(long, default_value = "0.01")]
+        rmse: f32,
+
+        /// Largest single channel difference, in [0, 1]
+        #[structopt(long, default_value = "0.25")]
+        max_diff: f32,
+    },
+}
@@ -0,0 +1,203 @@
+// Simulation time fed to the camera, background and effects. Follows the wall clock scaled
+// by `scale`, or advances a fixed step per frame, and can be paused, stepped and seeked so
+// an effect can be inspected at an exact moment. Kept in f64 so long runs don't lose
+// precision, handed out as f32 like the rest of the frame.
+
+const MIN_SCALE: f64 = 1.0 / 64.0;
+const MAX_SCALE: f64 = 64.0;
+
+// step of a paused clock without a fixed step
+const DEFAULT_STEP: f64 = 1.0 / 60.0;
+
+#[derive(Debug, Clone)]
+pub struct Clock {
+    time: f64,
+    scale: f64,
+    paused: bool,
+    // seconds per frame regardless of the wall clock, `None` follows the wall clock
+    fixed_step: Option<f64>,
+    // frames to advance while paused
+    steps: u32,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock {
+            time: 0.0,
+            scale: 1.0,
+            paused: false,
+            fixed_step: None,
+            steps: 0,
+        }
+    }
+
+    // advances by one frame that took `elapsed` seconds of wall time, returns the new time
+    pub fn tick(&mut self, elapsed: f64) -> f32 {
+        let frame = self.fixed_step.unwrap_or(elapsed);
+        if !self.paused {
+            self.time += frame * self.scale;
+        } else if self.steps > 0 {
+            self.steps -= 1;
+            self.time += self.fixed_step.unwrap_or(DEFAULT_STEP) * self.scale;
+        }
+        self.time()
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time as f32
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        self.time = (time as f64).max(0.0);
+    }
+
+    // relative seek, clamped at 0
+    pub fn skip(&mut self, seconds: f32) {
+        self.time = (self.time + seconds as f64).max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.steps = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.set_paused(!self.paused);
+    }
+
+    // advances a single frame on the next tick, pauses a running clock first
+    pub fn step(&mut self) {
+        if !self.paused {
+            self.set_paused(true);
+        }
+        self.steps += 1;
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale as f32
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = (scale as f64).max(MIN_SCALE).min(MAX_SCALE);
+    }
+
+    pub fn faster(&mut self) {
+        self.set_scale((self.scale * 2.0) as f32);
+    }
+
+    pub fn slower(&mut self) {
+        self.set_scale((self.scale * 0.5) as f32);
+    }
+
+    // `fps` frames per simulated second regardless of the wall clock, `None` to follow it
+    pub fn set_fixed_rate(&mut self, fps: Option<f32>) {
+        self.fixed_step = fps.filter(|fps| *fps > 0.0).map(|fps| 1.0 / fps as f64);
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_wall_clock() {
+        let mut clock = Clock::new();
+        assert_eq!(clock.tick(0.5), 0.5);
+        assert_eq!(clock.tick(0.25), 0.75);
+    }
+
+    #[test]
+    fn pause_and_step() {
+        let mut clock = Clock::new();
+        clock.tick(1.0);
+        clock.toggle_pause();
+        assert!(clock.is_paused());
+        assert_eq!(clock.tick(1.0), 1.0);
+
+        // one default frame per step, then holds again
+        clock.step();
+        clock.step();
+        assert_eq!(clock.tick(1.0), (1.0 + DEFAULT_STEP) as f32);
+        assert_eq!(clock.tick(1.0), (1.0 + 2.0 * DEFAULT_STEP) as f32);
+        assert_eq!(clock.tick(1.0), (1.0 + 2.0 * DEFAULT_STEP) as f32);
+
+        // stepping a running clock pauses it, resuming drops pending steps
+        let mut clock = Clock::new();
+        clock.step();
+        assert!(clock.is_paused());
+        clock.step();
+        clock.set_paused(false);
+        assert_eq!(clock.tick(0.5), 0.5);
+    }
+
+    #[test]
+    fn time_scale() {
+        let mut clock = Clock::new();
+        clock.set_scale(2.0);
+        assert_eq!(clock.tick(0.5), 1.0);
+        clock.slower();
+        clock.slower();
+        assert_eq!(clock.scale(), 0.5);
+        assert_eq!(clock.tick(1.0), 1.5);
+
+        clock.set_scale(1000.0);
+        assert_eq!(clock.scale(), MAX_SCALE as f32);
+        clock.set_scale(0.0);
+        assert_eq!(clock.scale(), MIN_SCALE as f32);
+    }
+
+    #[test]
+    fn fixed_rate() {
+        let mut clock = Clock::new();
+        clock.set_fixed_rate(Some(4.0));
+        // wall time is ignored, 4 frames make a simulated second
+        for _ in 0..4 {
+            clock.tick(10.0);
+        }
+        assert_eq!(clock.time(), 1.0);
+
+        // steps advance by the fixed step too
+        clock.step();
+        assert_eq!(clock.tick(10.0), 1.25);
+
+        clock.set_paused(false);
+        clock.set_fixed_rate(None);
+        assert_eq!(clock.tick(0.5), 1.75);
+        clock.set_fixed_rate(Some(0.0));
+        assert_eq!(clock.tick(0.25), 2.0);
+    }
+
+    #[test]
+    fn fixed_rate_accumulates_without_drift() {
+        let mut clock = Clock::new();
+        clock.set_fixed_rate(Some(60.0));
+        for _ in 0..60 * 3600 {
+            clock.tick(0.0);
+        }
+        assert!((clock.time() - 3600.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn seek() {
+        let mut clock = Clock::new();
+        clock.seek(12.5);
+        assert_eq!(clock.time(), 12.5);
+        assert_eq!(clock.tick(0.5), 13.0);
+        clock.skip(-20.0);
+        assert_eq!(clock.time(), 0.0);
+        clock.seek(-1.0);
+        assert_eq!(clock.time(), 0.0);
+        clock.skip(2.0);
+        assert_eq!(clock.time(), 2.0);
+    }
+}
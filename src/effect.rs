@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+use crate::particles;
+use crate::gradient;
+use crate::palette;
+use crate::simd;
+use crate::fx_field;
+use crate::fx_lines;
+use crate::fx_spiral;
+
+#[derive(Default)]
+pub struct Settings {
+    pub palettes: palette::Library,
+    // palette per effect name, replacing the effect's default palette
+    pub palette_overrides: HashMap<String, String>,
+    // particle count replacing the effects' defaults, rounded to fit each effect's layout
+    pub particles: Option<u32>,
+    // keeps the particle updates on the calling thread
+    pub single_threaded: bool,
+}
+
+impl Settings {
+    // library palette `effect` starts with, its registered default unless overridden
+    pub fn palette_name(&self, effect: &str) -> String {
+        match self.palette_overrides.get(effect) {
+            Some(name) => name.clone(),
+            None => find(effect).map(|fx| fx.palette).unwrap_or_default().to_string(),
+        }
+    }
+}
+
+// one particle at one moment, what `update` writes into the instance buffers
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ParticleState {
+    // position and billboard size
+    pub posscale: [f32; 4],
+    pub color: [f32; 4],
+}
+
+// effects only simulate, drawing goes through the shared `particles::ParticleRenderer`.
+// `evaluate` and `evaluate_lanes` are pure functions of time and index, frames can be
+// computed in any order and particles on any thread
+pub trait Effect: Sync {
+    fn new(settings: &Settings, gradient: gradient::Gradient) -> Self where Self: Sized;
+    fn name(&self) -> &'static str;
+    // number of particles, `evaluate` takes indices below it
+    fn capacity(&self) -> u32;
+    fn dof(&self) -> particles::Dof;
+    fn set_alpha(&mut self, alpha: f32);
+    fn set_gradient(&mut self, gradient: gradient::Gradient);
+    // particle `index` at `time`, one lane of `evaluate_lanes` computed on its own
+    fn evaluate(&self, time: f32, index: u32) -> ParticleState;
+    // particles `first..first + simd::LANES` at `time`, indices past the capacity are
+    // allowed and ignored by the caller
+    fn evaluate_lanes(&self, time: f32, first: u32) -> [ParticleState; simd::LANES];
+
+    fn update(&self, particles: &mut particles::ParticleWriter, time: f32) {
+        particles.fill(self.capacity(), |first, run| {
+            for local in (0..run.capacity()).step_by(simd::LANES) {
+                let states = self.evaluate_lanes(time, first + local);
+                for (i, state) in states.iter().enumerate() {
+                    let index = local as usize + i;
+                    if !run.set_position(index, state.posscale) {
+                        return;
+                    }
+                    run.set_color(index, state.color);
+                }
+            }
+        });
+    }
+}
+
+pub struct Registration {
+    pub name: &'static str,
+    // name of the palette in the palette library used unless overridden
+    pub palette: &'static str,
+    pub create: fn(&Settings, gradient::Gradient) -> Box<dyn Effect>,
+}
+
+fn create_boxed<T: Effect + 'static>(settings: &Settings, gradient: gradient::Gradient) -> Box<dyn Effect> {
+    Box::new(T::new(settings, gradient))
+}
+
+// every effect available to the binary, new effects only need an entry here
+pub const EFFECTS: &[Registration] = &[
+    Registration { name: fx_lines::NAME, palette: fx_lines::PALETTE, create: create_boxed::<fx_lines::Lines> },
+    Registration { name: fx_field::NAME, palette: fx_field::PALETTE, create: create_boxed::<fx_field::Field> },
+    Registration { name: fx_spiral::NAME, palette: fx_spiral::PALETTE, create: create_boxed::<fx_spiral::Spiral> },
+];
+
+pub const DEFAULT: &str = fx_spiral::NAME;
+
+pub fn find(name: &str) -> Option<&'static Registration> {
+    EFFECTS.iter().find(|fx| fx.name == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    EFFECTS.iter().map(|fx| fx.name).collect()
+}
+
+pub fn lookup(name: &str) -> Result<&'static Registration> {
+    find(name).ok_or_else(|| anyhow!("unknown effect '{}', available: {}", name, names().join(", ")))
+}
+
+pub fn create(name: &str, settings: &Settings) -> Result<Box<dyn Effect>> {
+    let fx = lookup(name)?;
+    let gradient = settings.palettes.lookup(&settings.palette_name(fx.name))?.clone();
+    Ok((fx.create)(settings, gradient))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    // not a multiple of `simd::LANES` nor of the parallel fill chunks
+    const PARTICLES: u32 = 9_999;
+    const TIMES: &[f32] = &[0.0, 0.7, 3.5, 120.25];
+
+    fn effects() -> Vec<Box<dyn Effect>> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(palette::DEFAULT_PATH);
+        let settings = Settings {
+            palettes: palette::Library::load(&path).unwrap(),
+            particles: Some(PARTICLES),
+            ..Default::default()
+        };
+        names().iter().map(|name| create(name, &settings).unwrap()).collect()
+    }
+
+    fn update(effect: &dyn Effect, time: f32) -> Vec<ParticleState> {
+        let capacity = effect.capacity() as usize;
+        let mut positions = vec![0.0; capacity * 4];
+        let mut colors = vec![0.0; capacity * 4];
+        let mut writer = particles::ParticleWriter::new(&mut positions, &mut colors);
+        effect.update(&mut writer, time);
+        assert_eq!(writer.count(), capacity as u32);
+
+        let mut states = Vec::with_capacity(capacity);
+        for (posscale, color) in positions.chunks_exact(4).zip(colors.chunks_exact(4)) {
+            let mut state = ParticleState::default();
+            state.posscale.copy_from_slice(posscale);
+            state.color.copy_from_slice(color);
+            states.push(state);
+        }
+        states
+    }
+
+    #[test]
+    fn evaluate_matches_update() {
+        for effect in effects() {
+            for &time in TIMES {
+                for (index, state) in update(effect.as_ref(), time).iter().enumerate() {
+                    assert_eq!(effect.evaluate(time, index as u32), *state, "{} at {} particle {}", effect.name(), time, index);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn order_independent() {
+        for effect in effects() {
+            let forward: Vec<Vec<ParticleState>> = TIMES.iter().map(|&time| update(effect.as_ref(), time)).collect();
+            for (&time, frame) in TIMES.iter().zip(forward.iter()).rev() {
+                for index in (0..effect.capacity()).rev() {
+                    assert_eq!(effect.evaluate(time, index), frame[index as usize], "{} at {} particle {}", effect.name(), time, index);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn repeatable() {
+        for effect in effects() {
+            for &time in TIMES {
+                assert!(update(effect.as_ref(), time) == update(effect.as_ref(), time), "{} at {}", effect.name(), time);
+                assert_eq!(effect.evaluate_lanes(time, 8), effect.evaluate_lanes(time, 8), "{} at {}", effect.name(), time);
+            }
+        }
+    }
+}
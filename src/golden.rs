@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Context, Result};
+use flink::{Vec2};
+use std::path::{Path, PathBuf};
+
+use crate::camera;
+use crate::effect;
+use crate::fx_field;
+use crate::fx_lines;
+use crate::fx_spiral;
+use crate::raster;
+
+// visual regression cases, rendered with the software rasterizer and compared to golden
+// PNGs, `check --bless` rewrites the goldens after an intended change
+
+pub const DEFAULT_DIR: &str = "assets/golden";
+pub const DEFAULT_OUTPUT: &str = "target/golden";
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+const FOV: f32 = 0.40;
+const DISTANCE: f32 = 5.0;
+
+pub struct Case {
+    pub effect: &'static str,
+    pub time: f32,
+    pub seed: u32,
+}
+
+pub const CASES: &[Case] = &[
+    Case { effect: fx_lines::NAME, time: 0.0, seed: 1 },
+    Case { effect: fx_lines::NAME, time: 3.5, seed: 1 },
+    Case { effect: fx_field::NAME, time: 0.0, seed: 1 },
+    Case { effect: fx_field::NAME, time: 3.5, seed: 1 },
+    Case { effect: fx_spiral::NAME, time: 0.0, seed: 1 },
+    Case { effect: fx_spiral::NAME, time: 3.5, seed: 1 },
+];
+
+impl Case {
+    pub fn name(&self) -> String {
+        format!("{}-{:.2}-{}", self.effect, self.time, self.seed)
+    }
+
+    pub fn render(&self, rasterizer: &raster::Rasterizer, settings: &effect::Settings) -> Result<image::RgbaImage> {
+        let mut camera = camera::Camera::new(FOV, DISTANCE);
+        camera.update_view(Vec2 { x: 0.0, y: 0.0 }, WIDTH as f32, HEIGHT as f32);
+
+        // effects take the global seed when they are created, leave it as it was
+        let seed = common::random::seed();
+        common::random::set_seed(self.seed);
+        let effect = effect::create(self.effect, settings);
+        common::random::set_seed(seed);
+        let effect = effect?;
+        let frame = rasterizer.render(&camera, effect.as_ref(), self.time, WIDTH, HEIGHT);
+        Ok(frame.to_image(true))
+    }
+}
+
+// channel differences in [0, 1], alpha included
+#[derive(Debug, Clone, Copy)]
+pub struct Difference {
+    pub rmse: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    pub rmse: f32,
+    pub max: f32,
+}
+
+impl Difference {
+    pub fn within(&self, tolerance: Tolerance) -> bool {
+        self.rmse <= tolerance.rmse && self.max <= tolerance.max
+    }
+}
+
+// `None` if the sizes don't match
+pub fn compare(actual: &image::RgbaImage, golden: &image::RgbaImage) -> Option<Difference> {
+    if actual.dimensions() != golden.dimensions() {
+        return None;
+    }
+    let mut sum = 0.0f64;
+    let mut max = 0u8;
+    for (a, b) in actual.as_raw().iter().zip(golden.as_raw().iter()) {
+        let d = (*a as i32 - *b as i32).abs() as u8;
+        sum += (d as f64 / 255.0).powi(2);
+        max = max.max(d);
+    }
+    let count = actual.as_raw().len().max(1) as f64;
+    Some(Difference {
+        rmse: (sum / count).sqrt() as f32,
+        max: max as f32 / 255.0,
+    })
+}
+
+// per pixel largest channel difference, amplified so small drifts stay visible
+pub fn diff_image(actual: &image::RgbaImage, golden: &image::RgbaImage) -> image::RgbaImage {
+    let (width, height) = actual.dimensions();
+    image::RgbaImage::from_fn(width, height, |x, y| {
+        let a = actual.get_pixel(x, y);
+        let d = if x < golden.width() && y < golden.height() {
+            let b = golden.get_pixel(x, y);
+            (0..4).map(|c| (a[c] as i32 - b[c] as i32).abs()).max().unwrap_or(0)
+        } else {
+            255
+        };
+        let v = (d * 8).min(255) as u8;
+        image::Rgba([v, 0, if d > 0 { 64 } else { 0 }, 255])
+    })
+}
+
+fn save(img: &image::RgbaImage, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    img.save(path).with_context(|| format!("failed to write {}", path.display()))
+}
+
+// renders every case, with `bless` the goldens are replaced instead of checked.
+// failures write `<case>.actual.png` and `<case>.diff.png` into `output`
+pub fn check(settings: &effect::Settings, golden_dir: &Path, output: &Path, tolerance: Tolerance, bless: bool) -> Result<()> {
+    let rasterizer = raster::Rasterizer::new()?;
+    let mut failed = Vec::new();
+
+    for case in CASES {
+        let name = case.name();
+        let actual = case.render(&rasterizer, settings)?;
+        let golden_path: PathBuf = golden_dir.join(format!("{}.png", name));
+
+        if bless {
+            save(&actual, &golden_path)?;
+            println!("{:<24} blessed", name);
+            continue;
+        }
+
+        let golden = match image::open(&golden_path) {
+            Ok(golden) => golden.to_rgba8(),
+            Err(err) => {
+                println!("{:<24} FAILED, no golden image ({}), run `check --bless`", name, err);
+                failed.push(name);
+                continue;
+            }
+        };
+
+        match compare(&actual, &golden) {
+            Some(diff) if diff.within(tolerance) => {
+                println!("{:<24} ok     rmse {:.5} max {:.3}", name, diff.rmse, diff.max);
+            }
+            result => {
+                match result {
+                    Some(diff) => println!("{:<24} FAILED rmse {:.5} max {:.3}", name, diff.rmse, diff.max),
+                    None => println!("{:<24} FAILED size {:?}, golden {:?}", name, actual.dimensions(), golden.dimensions()),
+                }
+                save(&actual, &output.join(format!("{}.actual.png", name)))?;
+                save(&diff_image(&actual, &golden), &output.join(format!("{}.diff.png", name)))?;
+                failed.push(name);
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} of {} golden checks failed: {}, see {}", failed.len(), CASES.len(), failed.join(", "), output.display()))
+    }
+}
@@ -0,0 +1,347 @@
+use serde::Deserialize;
+use shader::math::{lerp, smoothstep};
+use std::convert::TryFrom;
+
+// color gradients sampled by the effects, colors are linear rgba and may go above 1.0 (hdr)
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Edge {
+    Clamp,
+    Repeat,
+    Mirror,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    Linear,
+    Smoothstep,
+    Constant,
+}
+
+// color space the stops are blended in, alpha is always blended linearly
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Blend {
+    Rgb,
+    Oklab,
+    Hsv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stop {
+    pub position: f32,
+    pub color: [f32; 4],
+}
+
+impl Stop {
+    pub fn new(position: f32, color: [f32; 4]) -> Self {
+        Stop {
+            position: position,
+            color: color,
+        }
+    }
+}
+
+impl Default for Edge {
+    fn default() -> Self {
+        Edge::Clamp
+    }
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation::Linear
+    }
+}
+
+impl Default for Blend {
+    fn default() -> Self {
+        Blend::Rgb
+    }
+}
+
+// text form, stops are [r, g, b, a, position]
+#[derive(Debug, Clone, Deserialize)]
+struct GradientDesc {
+    stops: Vec<[f32; 5]>,
+    #[serde(default)]
+    edge: Edge,
+    #[serde(default)]
+    interpolation: Interpolation,
+    #[serde(default)]
+    blend: Blend,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "GradientDesc")]
+pub struct Gradient {
+    stops: Vec<Stop>,
+    pub edge: Edge,
+    pub interpolation: Interpolation,
+    pub blend: Blend,
+}
+
+fn rgb_to_oklab(c: [f32; 3]) -> [f32; 3] {
+    let l = 0.4122214708 * c[0] + 0.5363325363 * c[1] + 0.0514459929 * c[2];
+    let m = 0.2119034982 * c[0] + 0.6806995451 * c[1] + 0.1073969566 * c[2];
+    let s = 0.0883024619 * c[0] + 0.2817188376 * c[1] + 0.6299787005 * c[2];
+    let l = l.cbrt();
+    let m = m.cbrt();
+    let s = s.cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+fn oklab_to_rgb(c: [f32; 3]) -> [f32; 3] {
+    let l = c[0] + 0.3963377774 * c[1] + 0.2158037573 * c[2];
+    let m = c[0] - 0.1055613458 * c[1] - 0.0638541728 * c[2];
+    let s = c[0] - 0.0894841775 * c[1] - 1.2914855480 * c[2];
+    let l = l * l * l;
+    let m = m * m * m;
+    let s = s * s * s;
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+// hue in [0, 1)
+fn rgb_to_hsv(c: [f32; 3]) -> [f32; 3] {
+    let max = c[0].max(c[1]).max(c[2]);
+    let min = c[0].min(c[1]).min(c[2]);
+    let delta = max - min;
+    let h = if delta <= 0.0 {
+        0.0
+    } else if max == c[0] {
+        ((c[1] - c[2]) / delta).rem_euclid(6.0) / 6.0
+    } else if max == c[1] {
+        ((c[2] - c[0]) / delta + 2.0) / 6.0
+    } else {
+        ((c[0] - c[1]) / delta + 4.0) / 6.0
+    };
+    let s = if max > 0.0 { delta / max } else { 0.0 };
+    [h, s, max]
+}
+
+fn hsv_to_rgb(c: [f32; 3]) -> [f32; 3] {
+    let h = c[0].rem_euclid(1.0) * 6.0;
+    let chroma = c[2] * c[1];
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = c[2] - chroma;
+    [r + m, g + m, b + m]
+}
+
+fn blend(a: [f32; 4], b: [f32; 4], f: f32, mode: Blend) -> [f32; 4] {
+    // the color space round trips are not exact, the stops themselves are
+    if f <= 0.0 {
+        return a;
+    }
+    if f >= 1.0 {
+        return b;
+    }
+    let alpha = lerp(a[3], b[3], f);
+    let rgb = match mode {
+        Blend::Rgb => [lerp(a[0], b[0], f), lerp(a[1], b[1], f), lerp(a[2], b[2], f)],
+        Blend::Oklab => {
+            let a = rgb_to_oklab([a[0], a[1], a[2]]);
+            let b = rgb_to_oklab([b[0], b[1], b[2]]);
+            oklab_to_rgb([lerp(a[0], b[0], f), lerp(a[1], b[1], f), lerp(a[2], b[2], f)])
+        }
+        Blend::Hsv => {
+            let a = rgb_to_hsv([a[0], a[1], a[2]]);
+            let b = rgb_to_hsv([b[0], b[1], b[2]]);
+            // shortest way around the hue circle
+            let mut dh = b[0] - a[0];
+            if dh > 0.5 {
+                dh -= 1.0;
+            } else if dh < -0.5 {
+                dh += 1.0;
+            }
+            hsv_to_rgb([a[0] + dh * f, lerp(a[1], b[1], f), lerp(a[2], b[2], f)])
+        }
+    };
+    [rgb[0], rgb[1], rgb[2], alpha]
+}
+
+impl Gradient {
+    // stops get sorted by position, clamped linear rgb blending by default.
+    // panics without stops, there is no color to sample
+    pub fn new(mut stops: Vec<Stop>) -> Self {
+        assert!(!stops.is_empty(), "gradient without stops");
+        stops.sort_by(|a, b| a.position.partial_cmp(&b.position).unwrap_or(std::cmp::Ordering::Equal));
+        Gradient {
+            stops: stops,
+            edge: Edge::default(),
+            interpolation: Interpolation::default(),
+            blend: Blend::default(),
+        }
+    }
+
+    // maps `value` into the range covered by the stops
+    fn wrap(&self, value: f32) -> f32 {
+        let first = self.stops[0].position;
+        let last = self.stops[self.stops.len() - 1].position;
+        let span = last - first;
+        if span <= 0.0 {
+            return first;
+        }
+        match self.edge {
+            Edge::Clamp => value.max(first).min(last),
+            Edge::Repeat => first + (value - first).rem_euclid(span),
+            Edge::Mirror => {
+                let t = (value - first).rem_euclid(2.0 * span);
+                first + if t > span { 2.0 * span - t } else { t }
+            }
+        }
+    }
+
+    pub fn sample(&self, value: f32) -> [f32; 4] {
+        let t = self.wrap(value);
+
+        // first stop past `t`, stops are sorted
+        let i = self.stops.iter().position(|stop| stop.position > t).unwrap_or(self.stops.len());
+        if i == 0 {
+            return self.stops[0].color;
+        }
+        if i == self.stops.len() {
+            return self.stops[i - 1].color;
+        }
+
+        let a = &self.stops[i - 1];
+        let b = &self.stops[i];
+        let f = (t - a.position) / (b.position - a.position);
+        let f = match self.interpolation {
+            Interpolation::Linear => f,
+            Interpolation::Smoothstep => smoothstep(0.0, 1.0, f),
+            Interpolation::Constant => 0.0,
+        };
+        blend(a.color, b.color, f, self.blend)
+    }
+}
+
+impl TryFrom<GradientDesc> for Gradient {
+    type Error = &'static str;
+
+    fn try_from(desc: GradientDesc) -> Result<Self, Self::Error> {
+        if desc.stops.is_empty() {
+            return Err("gradient needs at least one stop");
+        }
+        let stops = desc.stops.iter().map(|s| Stop::new(s[4], [s[0], s[1], s[2], s[3]])).collect();
+        Ok(Gradient {
+            edge: desc.edge,
+            interpolation: desc.interpolation,
+            blend: desc.blend,
+            ..Gradient::new(stops)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+    fn gray(v: f32) -> [f32; 4] {
+        [v, v, v, 1.0]
+    }
+
+    fn ramp(edge: Edge, interpolation: Interpolation) -> Gradient {
+        Gradient {
+            edge: edge,
+            interpolation: interpolation,
+            ..Gradient::new(vec![Stop::new(0.0, BLACK), Stop::new(1.0, WHITE)])
+        }
+    }
+
+    #[test]
+    fn clamp_edge() {
+        let gradient = ramp(Edge::Clamp, Interpolation::Linear);
+        assert_eq!(gradient.sample(-0.5), BLACK);
+        assert_eq!(gradient.sample(1.5), WHITE);
+        assert_eq!(gradient.sample(0.25), gray(0.25));
+    }
+
+    #[test]
+    fn repeat_edge() {
+        let gradient = ramp(Edge::Repeat, Interpolation::Linear);
+        assert_eq!(gradient.sample(1.25), gray(0.25));
+        assert_eq!(gradient.sample(-0.75), gray(0.25));
+        assert_eq!(gradient.sample(3.0), BLACK);
+    }
+
+    #[test]
+    fn mirror_edge() {
+        let gradient = ramp(Edge::Mirror, Interpolation::Linear);
+        assert_eq!(gradient.sample(1.25), gray(0.75));
+        assert_eq!(gradient.sample(-0.25), gray(0.25));
+        assert_eq!(gradient.sample(2.0), BLACK);
+        assert_eq!(gradient.sample(3.0), WHITE);
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(ramp(Edge::Clamp, Interpolation::Constant).sample(0.75), BLACK);
+        assert_eq!(ramp(Edge::Clamp, Interpolation::Linear).sample(0.75), gray(0.75));
+        assert_eq!(ramp(Edge::Clamp, Interpolation::Smoothstep).sample(0.25), gray(0.15625));
+        assert_eq!(ramp(Edge::Clamp, Interpolation::Smoothstep).sample(0.5), gray(0.5));
+    }
+
+    #[test]
+    fn blends_hit_the_stops() {
+        let colors = [[0.9, 0.1, 0.05, 1.0], [0.02, 0.6, 0.3, 0.5], [0.2, 0.25, 1.4, 0.0]];
+        for &mode in &[Blend::Rgb, Blend::Oklab, Blend::Hsv] {
+            let stops = colors.iter().enumerate().map(|(i, color)| Stop::new(i as f32 * 0.5, *color)).collect();
+            let gradient = Gradient { blend: mode, ..Gradient::new(stops) };
+            for (i, color) in colors.iter().enumerate() {
+                assert_eq!(gradient.sample(i as f32 * 0.5), *color, "{:?} stop {}", mode, i);
+            }
+            // alpha blends linearly in every mode
+            assert!((gradient.sample(0.25)[3] - 0.75).abs() < 1e-6, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn unsorted_stops() {
+        let gradient = Gradient::new(vec![Stop::new(1.0, WHITE), Stop::new(0.5, gray(0.5)), Stop::new(0.0, BLACK)]);
+        assert_eq!(gradient.sample(0.25), gray(0.25));
+        assert_eq!(gradient.sample(0.75), gray(0.75));
+
+        let gradient: Gradient = toml::from_str("stops = [[1, 1, 1, 1, 1.0], [0, 0, 0, 1, 0.0]]").unwrap();
+        assert_eq!(gradient.sample(0.0), BLACK);
+        assert_eq!(gradient.sample(0.5), gray(0.5));
+    }
+
+    #[test]
+    fn single_stop() {
+        let gradient = Gradient::new(vec![Stop::new(0.3, WHITE)]);
+        assert_eq!(gradient.sample(-1.0), WHITE);
+        assert_eq!(gradient.sample(2.0), WHITE);
+    }
+
+    #[test]
+    fn empty_stops_rejected() {
+        assert!(toml::from_str::<Gradient>("stops = []").is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_stops_panic() {
+        Gradient::new(Vec::new());
+    }
+}
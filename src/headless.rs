@@ -0,0 +1,304 @@
+use anyhow::{anyhow, Result};
+use glutin::{ContextBuilder, PossiblyCurrent};
+use std::os::raw::c_void;
+
+// GL context without a window rendering into an offscreen framebuffer, so the grr pipeline
+// runs on CI machines that only have Mesa's software rasterizer. On Linux and the BSDs the
+// context comes from EGL on Mesa's surfaceless platform (llvmpipe, no display server needed),
+// falling back to OSMesa for older Mesa without it, elsewhere from glutin's headless context
+// which lives as long as its event loop. Frames are read back as images.
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+mod surfaceless {
+    use anyhow::{anyhow, bail, Result};
+    use glutin_egl_sys::egl;
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    // EGL_MESA_platform_surfaceless, not in the generated bindings
+    const PLATFORM_SURFACELESS_MESA: egl::types::EGLenum = 0x31DD;
+
+    // an EGL context without any surface, current on the creating thread
+    pub struct Context {
+        egl: egl::Egl,
+        display: egl::types::EGLDisplay,
+        context: egl::types::EGLContext,
+        _library: libloading::Library,
+    }
+
+    fn has_extension(extensions: *const std::os::raw::c_char, name: &str) -> bool {
+        !extensions.is_null() && unsafe { CStr::from_ptr(extensions) }.to_string_lossy().split(' ').any(|ext| ext == name)
+    }
+
+    impl Context {
+        pub fn new() -> Result<Self> {
+            let library = libloading::Library::new("libEGL.so.1").map_err(|err| anyhow!("failed to load libEGL: {}", err))?;
+            unsafe {
+                let get_proc_address = library
+                    .get::<unsafe extern "C" fn(*const std::os::raw::c_char) -> *const c_void>(b"eglGetProcAddress\0")
+                    .map_err(|err| anyhow!("libEGL without eglGetProcAddress: {}", err))?;
+                let egl = egl::Egl::load_with(|name| {
+                    let name = CString::new(name).unwrap();
+                    match library.get::<*const c_void>(name.as_bytes_with_nul()) {
+                        Ok(symbol) => *symbol,
+                        Err(_) => get_proc_address(name.as_ptr()),
+                    }
+                });
+
+                if !has_extension(egl.QueryString(egl::NO_DISPLAY, egl::EXTENSIONS as _), "EGL_MESA_platform_surfaceless") {
+                    bail!("EGL_MESA_platform_surfaceless not supported");
+                }
+                let display = egl.GetPlatformDisplay(PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY as *mut _, ptr::null());
+                if display == egl::NO_DISPLAY || egl.Initialize(display, ptr::null_mut(), ptr::null_mut()) == egl::FALSE {
+                    bail!("failed to initialize the surfaceless EGL display (0x{:x})", egl.GetError());
+                }
+
+                let context = Self::create(&egl, display);
+                if context.is_err() {
+                    egl.Terminate(display);
+                }
+                Ok(Context {
+                    egl: egl,
+                    display: display,
+                    context: context?,
+                    _library: library,
+                })
+            }
+        }
+
+        unsafe fn create(egl: &egl::Egl, display: egl::types::EGLDisplay) -> Result<egl::types::EGLContext> {
+            if !has_extension(egl.QueryString(display, egl::EXTENSIONS as _), "EGL_KHR_surfaceless_context") {
+                bail!("EGL_KHR_surfaceless_context not supported");
+            }
+            if egl.BindAPI(egl::OPENGL_API) == egl::FALSE {
+                bail!("EGL without desktop OpenGL");
+            }
+
+            // the default surface type is windows, which the surfaceless platform has none of
+            let config_attribs = [
+                egl::RENDERABLE_TYPE as i32,
+                egl::OPENGL_BIT as i32,
+                egl::SURFACE_TYPE as i32,
+                egl::PBUFFER_BIT as i32,
+                egl::NONE as i32,
+            ];
+            let mut config = ptr::null();
+            let mut count = 0;
+            if egl.ChooseConfig(display, config_attribs.as_ptr(), &mut config, 1, &mut count) == egl::FALSE || count == 0 {
+                bail!("no EGL config for desktop OpenGL");
+            }
+
+            // grr needs 4.5 core, SPIR-V shaders come from 4.6 or `GL_ARB_gl_spirv`
+            let context_attribs = [
+                egl::CONTEXT_MAJOR_VERSION as i32,
+                4,
+                egl::CONTEXT_MINOR_VERSION as i32,
+                5,
+                egl::CONTEXT_OPENGL_PROFILE_MASK as i32,
+                egl::CONTEXT_OPENGL_CORE_PROFILE_BIT as i32,
+                egl::NONE as i32,
+            ];
+            let context = egl.CreateContext(display, config, egl::NO_CONTEXT, context_attribs.as_ptr());
+            if context == egl::NO_CONTEXT {
+                bail!("failed to create an OpenGL 4.5 core context (0x{:x})", egl.GetError());
+            }
+            if egl.MakeCurrent(display, egl::NO_SURFACE, egl::NO_SURFACE, context) == egl::FALSE {
+                egl.DestroyContext(display, context);
+                bail!("failed to make the surfaceless context current (0x{:x})", egl.GetError());
+            }
+            Ok(context)
+        }
+
+        pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+            let symbol = CString::new(symbol).unwrap();
+            unsafe { self.egl.GetProcAddress(symbol.as_ptr()) as *const _ }
+        }
+    }
+
+    impl Drop for Context {
+        fn drop(&mut self) {
+            unsafe {
+                self.egl.MakeCurrent(self.display, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+                self.egl.DestroyContext(self.display, self.context);
+                self.egl.Terminate(self.display);
+            }
+        }
+    }
+}
+
+enum Context {
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+    Surfaceless(surfaceless::Context),
+    Glutin {
+        context: glutin::Context<PossiblyCurrent>,
+        // glutin's headless contexts outside Linux belong to the event loop they were built on,
+        // dropped after the context
+        _event_loop: Option<glutin::event_loop::EventLoop<()>>,
+    },
+}
+
+impl Context {
+    fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        match self {
+            #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+            Context::Surfaceless(context) => context.get_proc_address(symbol),
+            Context::Glutin { context, .. } => context.get_proc_address(symbol) as *const _,
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+fn create_context(width: u32, height: u32) -> Result<Context> {
+    use glutin::platform::unix::HeadlessContextExt;
+
+    let egl_err = match surfaceless::Context::new() {
+        Ok(context) => return Ok(Context::Surfaceless(context)),
+        Err(err) => err,
+    };
+    // OSMesa is gone since Mesa 25.1, still around on older installs
+    let context = ContextBuilder::new()
+        .build_osmesa(glutin::dpi::PhysicalSize::new(width, height))
+        .map_err(|err| anyhow!("no headless GL context, EGL surfaceless: {}, OSMesa: {}", egl_err, err))?;
+    let context = unsafe { context.make_current().map_err(|(_, err)| anyhow!("{}", err))? };
+    Ok(Context::Glutin {
+        context: context,
+        _event_loop: None,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+fn create_context(width: u32, height: u32) -> Result<Context> {
+    let el = glutin::event_loop::EventLoop::new();
+    let context = ContextBuilder::new().build_headless(&el, glutin::dpi::PhysicalSize::new(width, height))?;
+    let context = unsafe { context.make_current().map_err(|(_, err)| anyhow!("{}", err))? };
+    Ok(Context::Glutin {
+        context: context,
+        _event_loop: Some(el),
+    })
+}
+
+pub struct Headless {
+    // kept current for the lifetime of `grr`
+    _context: Context,
+    grr: grr::Device,
+    framebuffer: grr::Framebuffer,
+    color: grr::Image,
+    depth: grr::Image,
+    width: u32,
+    height: u32,
+}
+
+impl Headless {
+    // `srgb` encodes on write like the window's sRGB framebuffer
+    pub fn new(width: u32, height: u32, srgb: bool) -> Result<Self> {
+        let context = create_context(width, height)?;
+
+        unsafe {
+            let grr = grr::Device::new(
+                |symbol| context.get_proc_address(symbol),
+                grr::Debug::Disable,
+            );
+
+            let format = if srgb { grr::Format::R8G8B8A8_SRGB } else { grr::Format::R8G8B8A8_UNORM };
+            let extent = || grr::ImageType::D2 {
+                width: width,
+                height: height,
+                layers: 1,
+                samples: 1,
+            };
+            let color = grr.create_image(extent(), format, 1)?;
+            let depth = grr.create_image(extent(), grr::Format::D32_SFLOAT, 1)?;
+
+            let framebuffer = grr.create_framebuffer()?;
+            grr.bind_attachments(
+                framebuffer,
+                &[
+                    (grr::Attachment::Color(0), grr::AttachmentView::Image(color.as_view())),
+                    (grr::Attachment::Depth, grr::AttachmentView::Image(depth.as_view())),
+                ],
+            );
+            grr.set_color_attachments(framebuffer, &[0]);
+
+            Ok(Headless {
+                _context: context,
+                grr: grr,
+                framebuffer: framebuffer,
+                color: color,
+                depth: depth,
+                width: width,
+                height: height,
+            })
+        }
+    }
+
+    pub fn grr(&self) -> &grr::Device {
+        &self.grr
+    }
+
+    // binds and clears the offscreen target, as the window loop does with the default one
+    pub fn begin_frame(&self) {
+        unsafe {
+            self.grr.bind_framebuffer(self.framebuffer);
+            self.grr.set_viewport(
+                0,
+                &[grr::Viewport {
+                    x: 0.0,
+                    y: 0.0,
+                    w: self.width as _,
+                    h: self.height as _,
+                    n: 0.0,
+                    f: 1.0,
+                }],
+            );
+            self.grr.set_scissor(
+                0,
+                &[grr::Region {
+                    x: 0,
+                    y: 0,
+                    w: self.width as _,
+                    h: self.height as _,
+                }],
+            );
+            self.grr.clear_attachment(self.framebuffer, grr::ClearAttachment::ColorFloat(0, [0.0, 0.0, 0.0, 1.0]));
+            self.grr.clear_attachment(self.framebuffer, grr::ClearAttachment::Depth(1.0));
+        }
+    }
+
+    // the rendered frame, rows top to bottom
+    pub fn read_image(&self) -> image::RgbaImage {
+        let row = self.width as usize * 4;
+        let mut pixels = vec![0u8; row * self.height as usize];
+        unsafe {
+            self.grr.bind_read_framebuffer(self.framebuffer);
+            self.grr.copy_attachment_to_host(
+                grr::Region {
+                    x: 0,
+                    y: 0,
+                    w: self.width as _,
+                    h: self.height as _,
+                },
+                grr::MemoryLayout {
+                    base_format: grr::BaseFormat::RGBA,
+                    format_layout: grr::FormatLayout::U8,
+                    row_length: self.width,
+                    image_height: self.height,
+                    alignment: 4,
+                },
+                &mut pixels,
+            );
+        }
+
+        // GL reads bottom to top
+        let flipped: Vec<u8> = pixels.chunks(row).rev().flatten().copied().collect();
+        image::RgbaImage::from_raw(self.width, self.height, flipped).expect("pixel buffer matches the frame size")
+    }
+
+    pub fn destroy(&mut self) {
+        unsafe {
+            self.grr.delete_framebuffer(self.framebuffer);
+            self.grr.delete_image(self.color);
+            self.grr.delete_image(self.depth);
+        }
+    }
+}
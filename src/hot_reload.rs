@@ -0,0 +1,131 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::background;
+use crate::layout;
+use crate::particles;
+use crate::reflect;
+
+// Development mode: a thread polls the shader sources, rebuilds the SPIR-V with
+// `spirv-builder` when they change and hands the module to the render loop, which checks it
+// against the host pipelines and swaps them. Needs the `hot-reload` feature.
+
+const POLL: Duration = Duration::from_millis(500);
+
+// sources `spirv::SHADER` is built from, relative to the crate root
+const SOURCES: &[&str] = &["shader/src", "common/src", "derive/src"];
+
+pub struct ShaderWatcher {
+    receiver: mpsc::Receiver<Result<Vec<u8>>>,
+}
+
+impl ShaderWatcher {
+    pub fn spawn() -> Result<Self> {
+        if !cfg!(feature = "hot-reload") {
+            bail!("built without shader hot reload, run with `--features hot-reload`");
+        }
+
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let dirs: Vec<PathBuf> = SOURCES.iter().map(|dir| root.join(dir)).collect();
+        let mut last = newest(&dirs)?;
+        let (sender, receiver) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("shader-watcher".to_string())
+            .spawn(move || loop {
+                thread::sleep(POLL);
+                // files can vanish while an editor saves, try again on the next poll
+                let modified = match newest(&dirs) {
+                    Ok(modified) => modified,
+                    Err(_) => continue,
+                };
+                if modified <= last {
+                    continue;
+                }
+                last = modified;
+
+                println!("shader sources changed, rebuilding");
+                let spirv = build(&root.join("shader"))
+                    .and_then(|path| std::fs::read(&path).with_context(|| format!("failed to read {}", path.display())));
+                if sender.send(spirv).is_err() {
+                    return;
+                }
+            })
+            .context("failed to start the shader watcher")?;
+
+        Ok(ShaderWatcher { receiver: receiver })
+    }
+
+    // latest rebuilt module, or why the build failed, since the previous call
+    pub fn poll(&self) -> Option<Result<Vec<u8>>> {
+        self.receiver.try_iter().last()
+    }
+}
+
+// checks `spirv` against the host pipelines and creates all of the new ones before swapping
+// them, so a changed interface or a failed link keeps the running pipelines
+pub fn reload(grr: &grr::Device, spirv: &[u8], background: &mut background::Background, renderer: &mut particles::ParticleRenderer) -> Result<()> {
+    let module = reflect::Module::parse(spirv)?;
+    let errors: Vec<String> = reflect::host_pipelines()
+        .iter()
+        .flat_map(|desc| reflect::validate(&module, desc))
+        .collect();
+    if !errors.is_empty() {
+        bail!("the shaders don't match the host pipelines:\n  {}", errors.join("\n  "));
+    }
+
+    unsafe {
+        let backgrounds = background::create_pipelines(grr, spirv)?;
+        let particles = match layout::create_pipeline(grr, &particles::PIPELINE, spirv) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                for pipeline in backgrounds {
+                    grr.delete_pipeline(pipeline);
+                }
+                return Err(err);
+            }
+        };
+        background.replace_pipelines(grr, backgrounds);
+        renderer.replace_pipeline(grr, particles);
+    }
+    Ok(())
+}
+
+// most recent modification time of the files below `dirs`
+fn newest(dirs: &[PathBuf]) -> Result<SystemTime> {
+    let mut newest = SystemTime::UNIX_EPOCH;
+    let mut pending = dirs.to_vec();
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("failed to read {}", dir.display()))? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                newest = newest.max(metadata.modified()?);
+            }
+        }
+    }
+    Ok(newest)
+}
+
+// same settings as `build.rs`, returns the path of the module
+#[cfg(feature = "hot-reload")]
+fn build(shader: &Path) -> Result<PathBuf> {
+    use spirv_builder::{MemoryModel, SpirvBuilder};
+
+    SpirvBuilder::new(shader)
+        .spirv_version(1, 0)
+        .memory_model(MemoryModel::GLSL450)
+        .print_metadata(false)
+        .build()
+        .map_err(|err| anyhow!("shader build failed: {}", err))
+}
+
+#[cfg(not(feature = "hot-reload"))]
+fn build(_shader: &Path) -> Result<PathBuf> {
+    Err(anyhow!("built without shader hot reload"))
+}
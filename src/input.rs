@@ -1,10 +1,11 @@
-use glutin::event::{ElementState};
+use glutin::event::{ElementState, VirtualKeyCode};
 use flink::{Vec2};
 
 pub struct Input {
     mouse: ElementState,
     mouse_delta: Vec2<f32>,
     mouse_pos: Vec2<f32>,
+    keys_pressed: Vec<VirtualKeyCode>,
 }
 
 impl Input {
@@ -13,6 +14,7 @@ impl Input {
             mouse: ElementState::Released,
             mouse_delta: Vec2::<f32> { x: 0.0, y: 0.0 },
             mouse_pos: Vec2::<f32> { x: 0.0, y: 0.0 },
+            keys_pressed: Vec::new(),
         }
     }
 
@@ -29,6 +31,12 @@ impl Input {
         }
     }
 
+    pub fn update_key(&mut self, key: VirtualKeyCode, state: ElementState) {
+        if state == ElementState::Pressed {
+            self.keys_pressed.push(key);
+        }
+    }
+
     /*
     pub fn mouse_delta(&self) -> Vec2::<f32> {
         self.mouse_delta
@@ -39,7 +47,13 @@ impl Input {
         self.mouse_pos
     }
  
+    // keys pressed since the last reset
+    pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
     pub fn reset_delta(&mut self) {
         self.mouse_delta = Vec2::<f32> { x: 0.0, y: 0.0 };
+        self.keys_pressed.clear();
     }
 }
\ No newline at end of file
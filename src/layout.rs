@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use common::layout::{BlockField, Std140, UniformBlock, VertexAttribute, VertexFormat, VertexLayout};
+
+// grr descriptions for the `common::layout` derives
+
+fn vertex_format(format: VertexFormat) -> grr::VertexFormat {
+    match format {
+        VertexFormat::X32Float => grr::VertexFormat::X32Float,
+        VertexFormat::Xy32Float => grr::VertexFormat::Xy32Float,
+        VertexFormat::Xyz32Float => grr::VertexFormat::Xyz32Float,
+        VertexFormat::Xyzw32Float => grr::VertexFormat::Xyzw32Float,
+    }
+}
+
+// shader interface of a pipeline as the host sets it up, `reflect::validate` checks it
+// against the compiled SPIR-V
+pub struct PipelineDesc {
+    pub name: &'static str,
+    // entry points in `spirv::SHADER`
+    pub vertex: &'static str,
+    pub fragment: &'static str,
+    // `VertexLayout::ATTRIBUTES` per vertex buffer binding
+    pub vertex_buffers: &'static [&'static [VertexAttribute]],
+    pub uniforms: &'static [UniformDesc],
+    // bindings of combined image samplers
+    pub samplers: &'static [u32],
+}
+
+pub struct UniformDesc {
+    pub binding: u32,
+    // `Std140::SIZE` of the block
+    pub size: usize,
+    pub fields: &'static [BlockField],
+}
+
+// attributes of all vertex buffers of `desc`, the binding is the index in `vertex_buffers`
+pub fn vertex_attributes(desc: &PipelineDesc) -> Vec<grr::VertexAttributeDesc> {
+    desc.vertex_buffers
+        .iter()
+        .enumerate()
+        .flat_map(|(binding, attributes)| {
+            attributes.iter().map(move |attribute| grr::VertexAttributeDesc {
+                location: attribute.location,
+                binding: binding as u32,
+                format: vertex_format(attribute.format),
+                offset: attribute.offset,
+            })
+        })
+        .collect()
+}
+
+// vertex and fragment entry points of `desc` linked into a pipeline, the shader objects are
+// released once linked
+pub unsafe fn create_pipeline(grr: &grr::Device, desc: &PipelineDesc, spirv: &[u8]) -> Result<grr::Pipeline> {
+    let vs = grr.create_shader(
+        grr::ShaderStage::Vertex,
+        grr::ShaderSource::Spirv {
+            entrypoint: desc.vertex,
+        },
+        spirv,
+        grr::ShaderFlags::VERBOSE,
+    )?;
+
+    let fs = match grr.create_shader(
+        grr::ShaderStage::Fragment,
+        grr::ShaderSource::Spirv {
+            entrypoint: desc.fragment,
+        },
+        spirv,
+        grr::ShaderFlags::VERBOSE,
+    ) {
+        Ok(fs) => fs,
+        Err(err) => {
+            grr.delete_shader(vs);
+            return Err(err.into());
+        }
+    };
+
+    let pipeline = grr.create_graphics_pipeline(
+        grr::VertexPipelineDesc {
+            vertex_shader: vs,
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            geometry_shader: None,
+            fragment_shader: Some(fs),
+        },
+        grr::PipelineFlags::VERBOSE,
+    );
+    grr.delete_shader(vs);
+    grr.delete_shader(fs);
+    pipeline.with_context(|| format!("failed to link the {} pipeline", desc.name))
+}
+
+pub fn vertex_buffer_view<T: VertexLayout>(buffer: grr::Buffer, input_rate: grr::InputRate) -> grr::VertexBufferView {
+    grr::VertexBufferView {
+        buffer: buffer,
+        offset: 0,
+        stride: T::STRIDE as _,
+        input_rate: input_rate,
+    }
+}
+
+// whole buffer holding one `T`
+pub fn uniform_range<T: UniformBlock>(buffer: grr::Buffer) -> grr::BufferRange {
+    grr::BufferRange {
+        buffer: buffer,
+        offset: 0,
+        size: <T as Std140>::SIZE as _,
+    }
+}
@@ -0,0 +1,25 @@
+pub mod input;
+pub mod image;
+pub mod camera;
+pub mod clock;
+pub mod background;
+pub mod bench;
+pub mod effect;
+pub mod golden;
+pub mod gradient;
+pub mod headless;
+pub mod hot_reload;
+pub mod layout;
+pub mod offline;
+pub mod palette;
+pub mod palette_import;
+pub mod particles;
+pub mod raster;
+pub mod reflect;
+pub mod simd;
+pub mod fx_field;
+pub mod fx_lines;
+pub mod fx_spiral;
+pub mod spirv;
+pub mod switcher;
+pub mod timeline;
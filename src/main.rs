@@ -1,26 +1,189 @@
+use anyhow::Context;
+use std::collections::HashMap;
 use std::time::Instant;
 
 //use flink::{f32x4, f32x4x4, vec3, vec4};
-use glutin::event::{DeviceEvent, Event, WindowEvent};
+use glutin::event::{DeviceEvent, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
+use structopt::StructOpt;
 
-mod input;
-mod image;
-mod camera;
-mod background;
-mod fx_field;
-mod fx_lines;
-mod fx_spiral;
+mod cli;
+
+use rust_snippets::{background, bench, camera, clock, effect, golden, hot_reload, input, offline, palette, particles, switcher, timeline};
+
+// seconds Left and Right seek
+const SEEK_STEP: f32 = 1.0;
+
+const EFFECT_KEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+// clock shortcuts: Space pauses, Period steps one frame, `[` `]` halve and double the speed,
+// Left and Right seek a second, Home goes back to the start, PageDown to the next scene
+fn update_clock(clock: &mut clock::Clock, input: &input::Input, timeline: Option<&timeline::Timeline>) {
+    let mut changed = true;
+    if input.key_pressed(VirtualKeyCode::Space) {
+        clock.toggle_pause();
+    } else if input.key_pressed(VirtualKeyCode::Period) {
+        clock.step();
+    } else if input.key_pressed(VirtualKeyCode::LBracket) {
+        clock.slower();
+    } else if input.key_pressed(VirtualKeyCode::RBracket) {
+        clock.faster();
+    } else if input.key_pressed(VirtualKeyCode::Left) {
+        clock.skip(-SEEK_STEP);
+    } else if input.key_pressed(VirtualKeyCode::Right) {
+        clock.skip(SEEK_STEP);
+    } else if input.key_pressed(VirtualKeyCode::Home) {
+        clock.seek(0.0);
+    } else if input.key_pressed(VirtualKeyCode::PageDown) {
+        match timeline.and_then(|timeline| timeline.next_start(clock.time())) {
+            Some(start) => clock.seek(start),
+            None => changed = false,
+        }
+    } else {
+        changed = false;
+    }
+    if changed {
+        let state = if clock.is_paused() { "paused" } else { "running" };
+        println!("time {:.3}s, {}, scale {}x", clock.time(), state, clock.scale());
+    }
+}
+
+fn offline_renderer(opts: &cli::Options, effect_name: &str, settings: &effect::Settings, size: cli::Size, sky_variant: usize, gpu: bool) -> anyhow::Result<offline::OfflineRenderer> {
+    let view = offline::View {
+        width: size.width,
+        height: size.height,
+        fov: opts.fov,
+        distance: opts.distance,
+        srgb: !opts.no_srgb,
+        sky_variant: sky_variant,
+    };
+    if gpu {
+        offline::OfflineRenderer::gpu(view, effect_name, settings)
+    } else {
+        offline::OfflineRenderer::software(view, effect_name, settings)
+    }
+}
 
 fn main() -> anyhow::Result<()> {
+    let opts = cli::Options::from_args();
+    match opts.command {
+        Some(cli::Command::ListEffects) => {
+            for name in effect::names() {
+                println!("{}", name);
+            }
+            return Ok(());
+        }
+        Some(cli::Command::ListPalettes) => {
+            for name in palette::Library::load(&opts.palettes)?.names() {
+                println!("{}", name);
+            }
+            return Ok(());
+        }
+        _ => (),
+    }
+
+    let timeline = match &opts.timeline {
+        Some(path) => Some(timeline::Timeline::load(path)?),
+        None => None,
+    };
+    let effect_name = match &timeline {
+        Some(timeline) => timeline.scenes[0].effect.clone(),
+        None => opts.effect.clone().unwrap_or(effect::DEFAULT.to_string()),
+    };
+    effect::lookup(&effect_name)?;
+    let sky_variant = background::find_variant(&opts.sky)?;
+
+    // `--palette name` applies to the starting effect, `--palette effect=name` to any
+    let palettes = palette::Library::load(&opts.palettes)?;
+
+    // goldens only depend on the palette library, not on the other options
+    if let Some(cli::Command::Check { bless, golden, output, rmse, max_diff }) = &opts.command {
+        let settings = effect::Settings {
+            palettes: palettes,
+            ..Default::default()
+        };
+        let tolerance = golden::Tolerance { rmse: *rmse, max: *max_diff };
+        return golden::check(&settings, golden, output, tolerance, *bless);
+    }
+
+    let mut palette_overrides = HashMap::new();
+    for entry in &opts.palette {
+        let mut parts = entry.splitn(2, '=');
+        let (effect, name) = match (parts.next(), parts.next()) {
+            (Some(effect), Some(name)) => (effect.to_string(), name),
+            (Some(name), None) => (effect_name.clone(), name),
+            _ => unreachable!(),
+        };
+        effect::lookup(&effect)?;
+        palettes.lookup(name)?;
+        palette_overrides.insert(effect, name.to_string());
+    }
+    for (i, scene) in timeline.iter().flat_map(|timeline| timeline.scenes.iter().enumerate()) {
+        if let Some(palette) = &scene.palette {
+            palettes.resolve(palette).with_context(|| format!("scene {}", i))?;
+        }
+    }
+    common::random::set_seed(opts.seed);
+    let settings = effect::Settings {
+        palettes: palettes,
+        palette_overrides: palette_overrides,
+        particles: opts.particles,
+        single_threaded: opts.single_threaded,
+    };
+
+    match &opts.command {
+        Some(cli::Command::Bench { frames }) => {
+            let effects = match &opts.effect {
+                Some(name) => vec![name.as_str()],
+                None => effect::names(),
+            };
+            return bench::run(&settings, &effects, *frames);
+        }
+        Some(cli::Command::Preview { time, output, gpu }) => {
+            let mut renderer = offline_renderer(&opts, &effect_name, &settings, opts.size, sky_variant, *gpu)?;
+            let image = renderer.render(*time);
+            renderer.destroy();
+            image.save(output).with_context(|| format!("failed to write {}", output.display()))?;
+            return Ok(());
+        }
+        Some(cli::Command::Render { start, end, fps, resolution, output, gpu }) => {
+            if timeline.is_some() {
+                anyhow::bail!("`render` draws a single effect, use `--effect` instead of `--timeline`");
+            }
+            let size = resolution.unwrap_or(opts.size);
+            let mut renderer = offline_renderer(&opts, &effect_name, &settings, size, sky_variant, *gpu)?;
+            let sequence = offline::Sequence { start: *start, end: *end, fps: *fps };
+            let result = offline::render_sequence(&mut renderer, sequence, output);
+            renderer.destroy();
+            return result;
+        }
+        _ => (),
+    }
+
     unsafe {
         let el = EventLoop::new();
+        let fullscreen = if opts.fullscreen {
+            Some(glutin::window::Fullscreen::Borderless(el.primary_monitor()))
+        } else {
+            None
+        };
         let wb = glutin::window::WindowBuilder::new()
             .with_title("rust - gpu - snippets")
-            .with_inner_size(glutin::dpi::LogicalSize::new(1024.0, 768.0));
+            .with_inner_size(glutin::dpi::LogicalSize::new(opts.size.width as f64, opts.size.height as f64))
+            .with_fullscreen(fullscreen);
         let window = glutin::ContextBuilder::new()
-            .with_srgb(true)
-            .with_multisampling(4)
+            .with_srgb(!opts.no_srgb)
+            .with_multisampling(opts.msaa)
             .build_windowed(wb, &el)?
             .make_current()
             .unwrap();
@@ -30,25 +193,46 @@ fn main() -> anyhow::Result<()> {
             grr::Debug::Disable,
         );
 
-        let begin = Instant::now();
-        let mut camera = camera::Camera::new(0.40, 5.0);
+        let mut last_frame = Instant::now();
+        let mut clock = clock::Clock::new();
+        clock.set_scale(opts.time_scale);
+        clock.set_fixed_rate(opts.fixed_fps);
+        clock.seek(opts.start_time);
+        let mut camera = camera::Camera::new(opts.fov, opts.distance);
         let mut input = input::Input::new();
 
         // Modules
         let mut background = background::Background::new(&grr)?;
-        //let mut effect = fx_field::Effect::new(&grr)?;
-        //let mut effect = fx_lines::Effect::new(&grr)?;
-        let mut effect = fx_spiral::Effect::new(&grr)?;
+        background.set_variant(sky_variant);
+        let mut renderer = particles::ParticleRenderer::new(&grr)?;
+        let watcher = if opts.hot_reload {
+            Some(hot_reload::ShaderWatcher::spawn()?)
+        } else {
+            None
+        };
+        let effect = effect::create(&effect_name, &settings)?;
+        let mut switcher = switcher::Switcher::new(&grr, effect, &settings, opts.crossfade, 0.0)?;
+        let mut active_scene: Option<(usize, f32)> = None;
         
         el.run(move |event, _, control_flow| {
 
             match event {
-                Event::LoopDestroyed => return,
+                Event::LoopDestroyed => {
+                    switcher.destroy(&grr);
+                    renderer.destroy(&grr);
+                    return;
+                }
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::Resized(physical_size) => {
                         window.resize(physical_size);
                     }
                     WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    WindowEvent::KeyboardInput {
+                        input: KeyboardInput { virtual_keycode: Some(key), state, .. },
+                        ..
+                    } => {
+                        input.update_key(key, state);
+                    }
                     _ => (),
                 },
                 Event::DeviceEvent { event, .. } => match event {
@@ -61,8 +245,68 @@ fn main() -> anyhow::Result<()> {
                     _ => (),
                 },
                 Event::MainEventsCleared => {
-                    let time = begin.elapsed().as_secs_f32();
+                    let now = Instant::now();
+                    update_clock(&mut clock, &input, timeline.as_ref());
+                    let time = clock.tick((now - last_frame).as_secs_f64());
+                    last_frame = now;
                     let size = window.window().inner_size();
+
+                    // timeline, scene changes restart the local time of the effect, gaps between
+                    // scenes and the time after the last one only draw the background
+                    let cue = timeline.as_ref().and_then(|timeline| timeline.cue(time));
+                    let blank = timeline.is_some() && cue.is_none();
+                    if blank {
+                        active_scene = None;
+                    }
+                    if let Some(cue) = cue {
+                        if active_scene != Some((cue.index, cue.start)) {
+                            active_scene = Some((cue.index, cue.start));
+                            let scene = cue.scene;
+                            if let Err(err) = switcher.switch_to(&grr, &scene.effect, &settings, cue.start) {
+                                eprintln!("{}", err);
+                            }
+                            switcher.restart(cue.start);
+                            if let Some(palette) = &scene.palette {
+                                match settings.palettes.resolve(palette) {
+                                    Ok(gradient) => switcher.set_palette(palette.name(), gradient),
+                                    Err(err) => eprintln!("{}", err),
+                                }
+                            }
+                            background.set_sky(scene.sky.unwrap_or(background::DEFAULT_SKY));
+                            let params = scene.camera.clone().unwrap_or(timeline::CameraParams { fov: None, distance: None });
+                            camera.set_fov(params.fov.unwrap_or(opts.fov));
+                            camera.set_distance(params.distance.unwrap_or(opts.distance));
+                        }
+                    }
+
+                    // effect switching, number keys pick an effect and tab cycles through them
+                    let names = effect::names();
+                    for (i, key) in EFFECT_KEYS.iter().enumerate().take(names.len()) {
+                        if input.key_pressed(*key) {
+                            if let Err(err) = switcher.switch_to(&grr, names[i], &settings, time) {
+                                eprintln!("{}", err);
+                            }
+                        }
+                    }
+                    if input.key_pressed(VirtualKeyCode::Tab) {
+                        if let Err(err) = switcher.switch_next(&grr, &settings, time) {
+                            eprintln!("{}", err);
+                        }
+                    }
+
+                    if input.key_pressed(VirtualKeyCode::P) {
+                        if let Some(name) = switcher.next_palette(&settings.palettes) {
+                            println!("palette: {}", name);
+                        }
+                    }
+
+                    if let Some(spirv) = watcher.as_ref().and_then(|watcher| watcher.poll()) {
+                        match spirv.and_then(|spirv| hot_reload::reload(&grr, &spirv, &mut background, &mut renderer)) {
+                            Ok(()) => println!("shaders reloaded"),
+                            Err(err) => eprintln!("{:#}, keeping the previous shaders", err),
+                        }
+                    }
+
                     camera.update(&grr, &input, size.width as f32, size.height as f32, time);
                     input.reset_delta();
         
@@ -78,7 +322,9 @@ fn main() -> anyhow::Result<()> {
         
                     // modules
                     background.update(&grr, &camera, &input, time);
-                    effect.update(&grr, &camera, &input, time);
+                    if !blank {
+                        switcher.update(&grr, &renderer, &camera, time);
+                    }
 
                     window.swap_buffers().unwrap();
                 },
@@ -0,0 +1,163 @@
+use anyhow::{anyhow, Context, Result};
+use flink::{Vec2};
+use std::path::{Path, PathBuf};
+
+use crate::background;
+use crate::camera;
+use crate::effect;
+use crate::headless;
+use crate::input;
+use crate::particles;
+use crate::raster;
+use crate::switcher;
+
+// Frames of one effect without a window, on the software rasterizer or through the GL
+// pipeline in a headless context. The view is the window's before any mouse input.
+
+pub struct View {
+    pub width: u32,
+    pub height: u32,
+    pub fov: f32,
+    pub distance: f32,
+    pub srgb: bool,
+    // index into `shader::background::SKIES`
+    pub sky_variant: usize,
+}
+
+struct Gpu {
+    headless: headless::Headless,
+    background: background::Background,
+    renderer: particles::ParticleRenderer,
+    switcher: switcher::Switcher,
+    input: input::Input,
+}
+
+enum Backend {
+    Software {
+        rasterizer: raster::Rasterizer,
+        effect: Box<dyn effect::Effect>,
+    },
+    Gpu(Gpu),
+}
+
+pub struct OfflineRenderer {
+    camera: camera::Camera,
+    view: View,
+    backend: Backend,
+}
+
+impl OfflineRenderer {
+    pub fn software(view: View, effect: &str, settings: &effect::Settings) -> Result<Self> {
+        let mut rasterizer = raster::Rasterizer::new()?;
+        rasterizer.set_parallel(!settings.single_threaded);
+        rasterizer.set_sky_variant(view.sky_variant);
+        let backend = Backend::Software {
+            rasterizer: rasterizer,
+            effect: effect::create(effect, settings)?,
+        };
+        Ok(Self::with_backend(view, backend))
+    }
+
+    pub fn gpu(view: View, effect: &str, settings: &effect::Settings) -> Result<Self> {
+        let headless = headless::Headless::new(view.width, view.height, view.srgb)?;
+        let grr = headless.grr();
+
+        let mut background = background::Background::new(grr)?;
+        background.set_variant(view.sky_variant);
+        let renderer = particles::ParticleRenderer::new(grr)?;
+        let switcher = switcher::Switcher::new(grr, effect::create(effect, settings)?, settings, 0.0, 0.0)?;
+
+        let backend = Backend::Gpu(Gpu {
+            headless: headless,
+            background: background,
+            renderer: renderer,
+            switcher: switcher,
+            input: input::Input::new(),
+        });
+        Ok(Self::with_backend(view, backend))
+    }
+
+    fn with_backend(view: View, backend: Backend) -> Self {
+        let mut camera = camera::Camera::new(view.fov, view.distance);
+        camera.update_view(Vec2 { x: 0.0, y: 0.0 }, view.width as f32, view.height as f32);
+        OfflineRenderer {
+            camera: camera,
+            view: view,
+            backend: backend,
+        }
+    }
+
+    // the frame at effect time `time`, rows top to bottom
+    pub fn render(&mut self, time: f32) -> image::RgbaImage {
+        let view = &self.view;
+        match &mut self.backend {
+            Backend::Software { rasterizer, effect } => {
+                let frame = rasterizer.render(&self.camera, effect.as_ref(), time, view.width, view.height);
+                frame.to_image(view.srgb)
+            }
+            Backend::Gpu(gpu) => {
+                let grr = gpu.headless.grr();
+                gpu.headless.begin_frame();
+                gpu.background.update(grr, &self.camera, &gpu.input, time);
+                gpu.switcher.update(grr, &gpu.renderer, &self.camera, time);
+                gpu.headless.read_image()
+            }
+        }
+    }
+
+    pub fn destroy(&mut self) {
+        if let Backend::Gpu(gpu) = &mut self.backend {
+            let grr = gpu.headless.grr();
+            gpu.switcher.destroy(grr);
+            gpu.renderer.destroy(grr);
+            gpu.headless.destroy();
+        }
+    }
+}
+
+// frames at a fixed rate over [start, end), times come from the frame index so they don't
+// drift and every run gives the same frames
+#[derive(Debug, Clone, Copy)]
+pub struct Sequence {
+    pub start: f32,
+    pub end: f32,
+    pub fps: f32,
+}
+
+impl Sequence {
+    pub fn frames(&self) -> u32 {
+        // the tolerance keeps f32 rounding of `end` from adding a frame
+        ((self.end as f64 - self.start as f64) * self.fps as f64 - 1e-4).ceil().max(0.0) as u32
+    }
+
+    pub fn time(&self, frame: u32) -> f32 {
+        (self.start as f64 + frame as f64 / self.fps as f64) as f32
+    }
+}
+
+// `<dir>/frame-00042.png`
+pub fn frame_path(dir: &Path, frame: u32) -> PathBuf {
+    dir.join(format!("frame-{:05}.png", frame))
+}
+
+// renders every frame of `sequence` into `dir` as numbered PNGs
+pub fn render_sequence(renderer: &mut OfflineRenderer, sequence: Sequence, dir: &Path) -> Result<()> {
+    if sequence.fps.is_nan() || sequence.fps <= 0.0 {
+        return Err(anyhow!("fps must be positive"));
+    }
+    if sequence.end.is_nan() || sequence.end <= sequence.start {
+        return Err(anyhow!("end ({}) must be after start ({})", sequence.end, sequence.start));
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let frames = sequence.frames();
+    for frame in 0..frames {
+        let image = renderer.render(sequence.time(frame));
+        let path = frame_path(dir, frame);
+        image.save(&path).with_context(|| format!("failed to write {}", path.display()))?;
+        if (frame + 1) % 50 == 0 || frame + 1 == frames {
+            println!("{}/{} frames", frame + 1, frames);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::gradient::{Blend, Edge, Gradient, Interpolation};
+use crate::palette_import;
+
+pub const DEFAULT_PATH: &str = "assets/palettes.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Palette {
+    pub name: String,
+    #[serde(flatten)]
+    pub gradient: Gradient,
+}
+
+// a palette given by name from the library, or written inline
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PaletteRef {
+    Named(String),
+    Inline(Gradient),
+}
+
+// palette read from a gradient strip image or a GIMP `.gpl` file, relative to the library file
+#[derive(Debug, Clone, Deserialize)]
+struct Import {
+    file: PathBuf,
+    // defaults to the `.gpl` name, or the file stem
+    name: Option<String>,
+    // number of stops sampled from an image strip
+    samples: Option<u32>,
+    edge: Option<Edge>,
+    interpolation: Option<Interpolation>,
+    blend: Option<Blend>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Library {
+    #[serde(default, rename = "palette")]
+    palettes: Vec<Palette>,
+    #[serde(default, rename = "import")]
+    imports: Vec<Import>,
+}
+
+impl PaletteRef {
+    // name in the library, empty for inline palettes
+    pub fn name(&self) -> &str {
+        match self {
+            PaletteRef::Named(name) => name,
+            PaletteRef::Inline(_) => "",
+        }
+    }
+}
+
+impl Import {
+    fn load(&self, dir: &Path) -> Result<Palette> {
+        let path = dir.join(&self.file);
+        let (gpl_name, mut gradient) = palette_import::load(&path, self.samples)?;
+        let name = match (&self.name, gpl_name) {
+            (Some(name), _) => name.clone(),
+            (None, Some(name)) => name,
+            (None, None) => path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default(),
+        };
+        gradient.edge = self.edge.unwrap_or(gradient.edge);
+        gradient.interpolation = self.interpolation.unwrap_or(gradient.interpolation);
+        gradient.blend = self.blend.unwrap_or(gradient.blend);
+        Ok(Palette {
+            name: name,
+            gradient: gradient,
+        })
+    }
+}
+
+impl Library {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read palettes {}", path.display()))?;
+        let mut library: Library = toml::from_str(&text)
+            .with_context(|| format!("failed to parse palettes {}", path.display()))?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for import in std::mem::take(&mut library.imports) {
+            let palette = import.load(dir)
+                .with_context(|| format!("failed to import palette {}", import.file.display()))?;
+            library.insert(palette);
+        }
+        Ok(library)
+    }
+
+    // replaces a palette of the same name
+    pub fn insert(&mut self, palette: Palette) {
+        match self.palettes.iter_mut().find(|p| p.name == palette.name) {
+            Some(existing) => *existing = palette,
+            None => self.palettes.push(palette),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.palettes.iter().map(|palette| palette.name.as_str()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Gradient> {
+        self.palettes.iter().find(|palette| palette.name == name).map(|palette| &palette.gradient)
+    }
+
+    pub fn lookup(&self, name: &str) -> Result<&Gradient> {
+        self.get(name).ok_or_else(|| anyhow!("unknown palette '{}', available: {}", name, self.names().join(", ")))
+    }
+
+    pub fn resolve(&self, palette: &PaletteRef) -> Result<Gradient> {
+        match palette {
+            PaletteRef::Named(name) => self.lookup(name).map(|gradient| gradient.clone()),
+            PaletteRef::Inline(gradient) => Ok(gradient.clone()),
+        }
+    }
+
+    // palette following `name`, wrapping around
+    pub fn next(&self, name: &str) -> Option<&Palette> {
+        if self.palettes.is_empty() {
+            return None;
+        }
+        let index = self.palettes.iter().position(|palette| palette.name == name).map(|i| i + 1).unwrap_or(0);
+        Some(&self.palettes[index % self.palettes.len()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradient::Stop;
+
+    fn palette(name: &str, gray: f32) -> Palette {
+        Palette {
+            name: name.to_string(),
+            gradient: Gradient::new(vec![Stop::new(0.0, [gray, gray, gray, 1.0])]),
+        }
+    }
+
+    #[test]
+    fn insert_replaces() {
+        let mut library = Library::default();
+        library.insert(palette("a", 0.0));
+        library.insert(palette("b", 0.5));
+        library.insert(palette("a", 1.0));
+        assert_eq!(library.names(), vec!["a", "b"]);
+        assert_eq!(library.get("a").unwrap().sample(0.0), [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn next_wraps() {
+        let mut library = Library::default();
+        assert!(library.next("a").is_none());
+        for name in &["a", "b", "c"] {
+            library.insert(palette(name, 0.0));
+        }
+        assert_eq!(library.next("a").unwrap().name, "b");
+        assert_eq!(library.next("c").unwrap().name, "a");
+        // unknown names and inline palettes start over at the first one
+        assert_eq!(library.next("").unwrap().name, "a");
+    }
+
+    #[test]
+    fn import_collisions() {
+        let dir = std::env::temp_dir().join(format!("rust-snippets-library-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("ember.gpl"), "GIMP Palette\nName: ember\n255 255 255\n").unwrap();
+        std::fs::write(dir.join("glow.gpl"), "GIMP Palette\nName: ember\n0 0 0\n").unwrap();
+        std::fs::write(
+            dir.join("palettes.toml"),
+            r#"
+            [[palette]]
+            name = "ember"
+            stops = [[0.5, 0.5, 0.5, 1.0, 0.0]]
+
+            [[palette]]
+            name = "other"
+            stops = [[0.5, 0.5, 0.5, 1.0, 0.0]]
+
+            # replaces the palette above
+            [[import]]
+            file = "ember.gpl"
+
+            # the `.gpl` name collides too, the explicit name wins
+            [[import]]
+            file = "glow.gpl"
+            name = "glow"
+            "#,
+        )
+        .unwrap();
+
+        let library = Library::load(&dir.join("palettes.toml")).unwrap();
+        assert_eq!(library.names(), vec!["ember", "other", "glow"]);
+        assert_eq!(library.get("ember").unwrap().sample(0.0), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(library.get("glow").unwrap().sample(0.0), [0.0, 0.0, 0.0, 1.0]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
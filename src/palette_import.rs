@@ -0,0 +1,197 @@
+use anyhow::{anyhow, Context, Result};
+use shader::post::srgb_to_linear;
+use std::path::Path;
+
+use crate::gradient::{Gradient, Stop};
+
+// stops taken from a strip when no sample count is given, more than enough for smooth strips
+const DEFAULT_SAMPLES: u32 = 32;
+
+// 8 bit srgb to the linear values the gradients work in
+fn to_linear(c: u8) -> f32 {
+    srgb_to_linear(c as f32 / 255.0)
+}
+
+fn evenly_spaced(colors: Vec<[f32; 4]>) -> Gradient {
+    let last = (colors.len().max(2) - 1) as f32;
+    let stops = colors.into_iter().enumerate().map(|(i, color)| Stop::new(i as f32 / last, color)).collect();
+    Gradient::new(stops)
+}
+
+// samples the middle row of a horizontal gradient strip, left to right,
+// vertical strips (taller than wide) are read top to bottom
+pub fn load_strip(path: &Path, samples: Option<u32>) -> Result<Gradient> {
+    let img = image::open(path)
+        .with_context(|| format!("failed to open palette image {}", path.display()))?
+        .to_rgba8();
+    let vertical = img.height() > img.width();
+    let length = if vertical { img.height() } else { img.width() };
+    let samples = samples.unwrap_or(DEFAULT_SAMPLES).max(1).min(length);
+
+    let colors = (0..samples)
+        .map(|i| {
+            let t = if samples > 1 { i as f32 / (samples - 1) as f32 } else { 0.0 };
+            let along = ((t * (length - 1) as f32).round() as u32).min(length - 1);
+            let pixel = if vertical {
+                img.get_pixel(img.width() / 2, along)
+            } else {
+                img.get_pixel(along, img.height() / 2)
+            };
+            [
+                to_linear(pixel[0]),
+                to_linear(pixel[1]),
+                to_linear(pixel[2]),
+                pixel[3] as f32 / 255.0,
+            ]
+        })
+        .collect();
+    Ok(evenly_spaced(colors))
+}
+
+// GIMP palette, returns the `Name:` header if there is one and the swatches as evenly spaced stops
+pub fn parse_gpl(text: &str) -> Result<(Option<String>, Gradient)> {
+    let mut lines = text.lines();
+    if lines.next().map(|line| line.trim()) != Some("GIMP Palette") {
+        return Err(anyhow!("missing 'GIMP Palette' header"));
+    }
+
+    let mut name = None;
+    let mut colors = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Name:") {
+            name = Some(value.trim().to_string());
+            continue;
+        }
+        if line.starts_with("Columns:") {
+            continue;
+        }
+
+        // `r g b [swatch name]`, the swatch name is ignored
+        let mut channels = line.split_whitespace().take(3).map(|v| v.parse::<u8>());
+        match (channels.next(), channels.next(), channels.next()) {
+            (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => {
+                colors.push([to_linear(r), to_linear(g), to_linear(b), 1.0]);
+            }
+            _ => return Err(anyhow!("line {}: expected '<r> <g> <b> [name]'", i + 2)),
+        }
+    }
+
+    if colors.is_empty() {
+        return Err(anyhow!("palette has no colors"));
+    }
+    Ok((name, evenly_spaced(colors)))
+}
+
+pub fn load_gpl(path: &Path) -> Result<(Option<String>, Gradient)> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read palette {}", path.display()))?;
+    parse_gpl(&text).with_context(|| format!("failed to parse palette {}", path.display()))
+}
+
+// picks the importer from the file extension, `.gpl` or any image format `image` can open
+pub fn load(path: &Path, samples: Option<u32>) -> Result<(Option<String>, Gradient)> {
+    let gpl = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.eq_ignore_ascii_case("gpl")).unwrap_or(false);
+    if gpl {
+        load_gpl(path)
+    } else {
+        Ok((None, load_strip(path, samples)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(r: u8, g: u8, b: u8) -> [f32; 4] {
+        [to_linear(r), to_linear(g), to_linear(b), 1.0]
+    }
+
+    #[test]
+    fn linear_conversion() {
+        assert_eq!(to_linear(0), 0.0);
+        assert_eq!(to_linear(255), 1.0);
+        assert!((to_linear(128) - 0.21586).abs() < 1e-5, "{}", to_linear(128));
+    }
+
+    #[test]
+    fn gpl() {
+        let text = "GIMP Palette\nName: Ember\nColumns: 4\n# comment\n\n  0   0   0\tBlack\n255 128 0 Orange\n255 255 255\n";
+        let (name, gradient) = parse_gpl(text).unwrap();
+        assert_eq!(name.as_deref(), Some("Ember"));
+        let stops = vec![Stop::new(0.0, rgb(0, 0, 0)), Stop::new(0.5, rgb(255, 128, 0)), Stop::new(1.0, rgb(255, 255, 255))];
+        assert_eq!(gradient, Gradient::new(stops));
+    }
+
+    #[test]
+    fn gpl_without_name() {
+        let (name, gradient) = parse_gpl("GIMP Palette\n10 20 30\n").unwrap();
+        assert_eq!(name, None);
+        assert_eq!(gradient.sample(0.0), rgb(10, 20, 30));
+        assert_eq!(gradient.sample(1.0), rgb(10, 20, 30));
+    }
+
+    #[test]
+    fn gpl_header() {
+        assert!(parse_gpl("").is_err());
+        assert!(parse_gpl("0 0 0\n").is_err());
+        assert!(parse_gpl("Name: Ember\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn gpl_malformed() {
+        let err = parse_gpl("GIMP Palette\nName: Ember\n0 0 0\n255 oops 0\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 4: expected '<r> <g> <b> [name]'");
+        let err = parse_gpl("GIMP Palette\n0 0\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: expected '<r> <g> <b> [name]'");
+        assert!(parse_gpl("GIMP Palette\n256 0 0\n").is_err());
+    }
+
+    #[test]
+    fn gpl_empty() {
+        assert!(parse_gpl("GIMP Palette\nName: Empty\n# nothing\n").is_err());
+    }
+
+    #[test]
+    fn strip() {
+        let dir = std::env::temp_dir().join(format!("rust-snippets-strip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 128], [255, 255, 255, 255]];
+
+        // only the middle row is read
+        let mut horizontal = image::RgbaImage::new(4, 3);
+        for (x, _, pixel) in horizontal.enumerate_pixels_mut() {
+            *pixel = image::Rgba(colors[x as usize]);
+        }
+        horizontal.put_pixel(0, 0, image::Rgba([9, 9, 9, 255]));
+        let path = dir.join("horizontal.png");
+        horizontal.save(&path).unwrap();
+
+        let mut vertical = image::RgbaImage::new(3, 4);
+        for (_, y, pixel) in vertical.enumerate_pixels_mut() {
+            *pixel = image::Rgba(colors[y as usize]);
+        }
+        let vertical_path = dir.join("vertical.png");
+        vertical.save(&vertical_path).unwrap();
+
+        let expected = Gradient::new(vec![
+            Stop::new(0.0, rgb(255, 0, 0)),
+            Stop::new(1.0 / 3.0, rgb(0, 255, 0)),
+            Stop::new(2.0 / 3.0, [0.0, 0.0, 1.0, 128.0 / 255.0]),
+            Stop::new(1.0, rgb(255, 255, 255)),
+        ]);
+        assert_eq!(load_strip(&path, Some(4)).unwrap(), expected);
+        assert_eq!(load_strip(&vertical_path, Some(4)).unwrap(), expected);
+
+        // more samples than pixels are clamped to the pixels
+        assert_eq!(load_strip(&path, None).unwrap(), expected);
+
+        let ends = Gradient::new(vec![Stop::new(0.0, rgb(255, 0, 0)), Stop::new(1.0, rgb(255, 255, 255))]);
+        assert_eq!(load_strip(&path, Some(2)).unwrap(), ends);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,428 @@
+use anyhow::{Result};
+use common::layout::{Std140, UniformBlock, VertexLayout};
+use common::locals::{LocalsParticles};
+use rayon::prelude::*;
+
+use crate::image;
+use crate::camera;
+use crate::layout;
+use crate::spirv;
+
+// corner of the particle quad, binding 0
+#[derive(Clone, Copy, VertexLayout)]
+#[repr(C)]
+pub struct QuadVertex {
+    #[vertex(location = 0)]
+    pub position: [f32; 3],
+}
+
+// quad drawn as a triangle strip
+pub const QUAD: [QuadVertex; 4] = [
+    QuadVertex { position: [-0.5, -0.5, 0.0] },
+    QuadVertex { position: [0.5, -0.5, 0.0] },
+    QuadVertex { position: [-0.5, 0.5, 0.0] },
+    QuadVertex { position: [0.5, 0.5, 0.0] },
+];
+
+// per instance data, positions at binding 1 and colors at binding 2.
+// only describe the buffers, `ParticleWriter` fills them as plain floats
+#[allow(dead_code)]
+#[derive(Clone, Copy, VertexLayout)]
+#[repr(C)]
+struct InstancePosition {
+    #[vertex(location = 1)]
+    posscale: [f32; 4],
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, VertexLayout)]
+#[repr(C)]
+struct InstanceColor {
+    #[vertex(location = 2)]
+    color: [f32; 4],
+}
+
+const BUFFER_STRIDE: u64 = InstancePosition::STRIDE as u64;
+
+pub const PIPELINE: layout::PipelineDesc = layout::PipelineDesc {
+    name: "particles",
+    vertex: shader::particles::VS,
+    fragment: shader::particles::FS,
+    vertex_buffers: &[QuadVertex::ATTRIBUTES, InstancePosition::ATTRIBUTES, InstanceColor::ATTRIBUTES],
+    uniforms: &[layout::UniformDesc {
+        binding: 0,
+        size: <LocalsParticles as Std140>::SIZE,
+        fields: <LocalsParticles as UniformBlock>::FIELDS,
+    }],
+    samplers: &[0],
+};
+
+// particles per task of a parallel `ParticleWriter::fill`, a multiple of `simd::LANES`
+const FILL_CHUNK: usize = 4096;
+
+// depth of field, particles away from `depth` grow and fade with `apperture`
+#[derive(Debug, Clone, Copy)]
+pub struct Dof {
+    pub depth: f32,
+    pub apperture: f32,
+}
+
+// bounds checked view over position/scale and color slices, 4 floats per particle,
+// keeps track of how many instances are live
+pub struct ParticleWriter<'a> {
+    positions: &'a mut [f32],
+    colors: &'a mut [f32],
+    capacity: u32,
+    count: u32,
+    parallel: bool,
+}
+
+impl<'a> ParticleWriter<'a> {
+    pub fn new(positions: &'a mut [f32], colors: &'a mut [f32]) -> Self {
+        let capacity = (positions.len().min(colors.len()) / 4) as u32;
+        ParticleWriter {
+            positions: positions,
+            colors: colors,
+            capacity: capacity,
+            count: 0,
+            parallel: true,
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    // one past the highest particle written with `set_position`
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    // `false` keeps `fill` on the calling thread
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    // returns false, writing nothing, if `index` is past the capacity
+    pub fn set_position(&mut self, index: usize, position: [f32; 4]) -> bool {
+        if index >= self.capacity as usize {
+            return false;
+        }
+        self.positions[index * 4..index * 4 + 4].copy_from_slice(&position);
+        self.count = self.count.max(index as u32 + 1);
+        true
+    }
+
+    // written next to the position of every particle each frame, returns false past the
+    // capacity. only `set_position` moves the live count
+    pub fn set_color(&mut self, index: usize, color: [f32; 4]) -> bool {
+        if index >= self.capacity as usize {
+            return false;
+        }
+        self.colors[index * 4..index * 4 + 4].copy_from_slice(&color);
+        true
+    }
+
+    // writes particles `0..count`, clamped to the capacity, by handing `f` the index of the
+    // first particle of a run and a writer over that run, indexed from 0. runs are split
+    // across the rayon pool unless `set_parallel(false)`, so `f` must not depend on the
+    // order of the calls
+    pub fn fill<F>(&mut self, count: u32, f: F)
+    where
+        F: Fn(u32, &mut ParticleWriter) + Sync,
+    {
+        let count = count.min(self.capacity) as usize;
+        let positions = &mut self.positions[..count * 4];
+        let colors = &mut self.colors[..count * 4];
+
+        let run = |first: usize, positions: &mut [f32], colors: &mut [f32]| {
+            let mut writer = ParticleWriter::new(positions, colors);
+            f(first as u32, &mut writer);
+            if writer.count > 0 { first as u32 + writer.count } else { 0 }
+        };
+        let written = if self.parallel {
+            positions
+                .par_chunks_mut(FILL_CHUNK * 4)
+                .zip(colors.par_chunks_mut(FILL_CHUNK * 4))
+                .enumerate()
+                .map(|(chunk, (positions, colors))| run(chunk * FILL_CHUNK, positions, colors))
+                .max()
+                .unwrap_or(0)
+        } else {
+            run(0, positions, colors)
+        };
+        self.count = self.count.max(written);
+    }
+}
+
+// per instance position/scale and color, 4 floats each
+pub struct ParticleBuffers {
+    positions: grr::Buffer,
+    colors: grr::Buffer,
+    capacity: u32,
+    count: u32,
+}
+
+impl ParticleBuffers {
+    pub fn new(grr: &grr::Device, capacity: u32) -> Result<Self> {
+        unsafe {
+            let positions = grr.create_buffer(
+                BUFFER_STRIDE * capacity as u64,
+                grr::MemoryFlags::DEVICE_LOCAL | grr::MemoryFlags::CPU_MAP_WRITE,
+            )?;
+
+            let colors = grr.create_buffer(
+                BUFFER_STRIDE * capacity as u64,
+                grr::MemoryFlags::DEVICE_LOCAL | grr::MemoryFlags::CPU_MAP_WRITE,
+            )?;
+
+            Ok(ParticleBuffers {
+                positions: positions,
+                colors: colors,
+                capacity: capacity,
+                count: 0,
+            })
+        }
+    }
+
+    // instances written by the last `write`
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    // maps both buffers for the duration of `f`, the live count is reset every write
+    pub fn write<F: FnOnce(&mut ParticleWriter)>(&mut self, grr: &grr::Device, f: F) {
+        unsafe {
+            let buffer_size = BUFFER_STRIDE * self.capacity as u64;
+            let positions = grr.map_buffer::<f32>(self.positions, 0..buffer_size, grr::MappingFlags::UNSYNCHRONIZED);
+            let colors = grr.map_buffer::<f32>(self.colors, 0..buffer_size, grr::MappingFlags::UNSYNCHRONIZED);
+            let mut writer = ParticleWriter::new(positions, colors);
+            f(&mut writer);
+            self.count = writer.count();
+            grr.unmap_buffer(self.positions);
+            grr.unmap_buffer(self.colors);
+        }
+    }
+
+    pub fn destroy(&mut self, grr: &grr::Device) {
+        unsafe {
+            grr.delete_buffer(self.positions);
+            grr.delete_buffer(self.colors);
+        }
+    }
+}
+
+pub struct ParticleRenderer {
+    texture: grr::Image,
+    vertices: grr::Buffer,
+    pipeline: grr::Pipeline,
+    vertex_array: grr::VertexArray,
+    sampler: grr::Sampler,
+}
+
+impl ParticleRenderer {
+    pub fn new(grr: &grr::Device) -> Result<Self> {
+        unsafe {
+            let texture = image::load_png("assets/particle.png", grr, grr::Format::R8G8B8A8_SRGB, true).unwrap();
+            let pipeline = layout::create_pipeline(grr, &PIPELINE, spirv::SHADER)?;
+
+            let vertex_array = grr.create_vertex_array(&layout::vertex_attributes(&PIPELINE)).unwrap();
+
+            let sampler = grr.create_sampler(grr::SamplerDesc {
+                min_filter: grr::Filter::Linear,
+                mag_filter: grr::Filter::Linear,
+                mip_map: None,
+                address: (
+                    grr::SamplerAddress::ClampBorder,
+                    grr::SamplerAddress::ClampBorder,
+                    grr::SamplerAddress::ClampBorder,
+                ),
+                lod_bias: 0.0,
+                lod: 0.0..10.0,
+                compare: None,
+                border_color: [0.0, 0.0, 0.0, 0.0],
+            }).unwrap();
+
+            let vertices = grr.create_buffer_from_host(
+                grr::as_u8_slice(&QUAD),
+                grr::MemoryFlags::DEVICE_LOCAL,
+            )
+            .unwrap();
+
+            Ok(ParticleRenderer {
+                texture: texture,
+                vertices: vertices,
+                pipeline: pipeline,
+                vertex_array: vertex_array,
+                sampler: sampler,
+            })
+        }
+    }
+
+    // swaps in the pipeline of a rebuilt shader module, created from `PIPELINE`, and deletes
+    // the running one
+    pub fn replace_pipeline(&mut self, grr: &grr::Device, pipeline: grr::Pipeline) {
+        unsafe {
+            grr.delete_pipeline(std::mem::replace(&mut self.pipeline, pipeline));
+        }
+    }
+
+    pub fn draw(&self, grr: &grr::Device, camera: &camera::Camera, buffers: &ParticleBuffers, dof: Dof) {
+        let num_particles = buffers.count();
+        if num_particles == 0 {
+            return;
+        }
+
+        unsafe {
+            let color_blend = grr::ColorBlend {
+                attachments: vec![grr::ColorBlendAttachment {
+                    blend_enable: true,
+                    color: grr::BlendChannel {
+                        src_factor: grr::BlendFactor::SrcAlpha,
+                        dst_factor: grr::BlendFactor::One,
+                        blend_op: grr::BlendOp::Add,
+                    },
+                    alpha: grr::BlendChannel {
+                        src_factor: grr::BlendFactor::SrcAlpha,
+                        dst_factor: grr::BlendFactor::One,
+                        blend_op: grr::BlendOp::Add,
+                    },
+                }],
+            };
+
+            let state_ds = grr::DepthStencil {
+                depth_test: true,
+                depth_write: false,
+                depth_compare_op: grr::Compare::LessEqual,
+                stencil_test: false,
+                stencil_front: grr::StencilFace::KEEP,
+                stencil_back: grr::StencilFace::KEEP,
+            };
+
+            // particles
+            let locals = LocalsParticles::new(camera.world_view_inv(), camera.view_proj(), dof.depth, dof.apperture);
+
+            let u_locals = grr.create_buffer_from_host(
+                grr::as_u8_slice(&[locals]),
+                grr::MemoryFlags::DEVICE_LOCAL,
+            )
+            .unwrap();
+
+            grr.bind_pipeline(self.pipeline);
+            grr.bind_depth_stencil_state(&state_ds);
+            grr.bind_color_blend_state(&color_blend);
+            grr.bind_vertex_array(self.vertex_array);
+            grr.bind_vertex_buffers(
+                self.vertex_array,
+                0,
+                &[
+                    layout::vertex_buffer_view::<QuadVertex>(self.vertices, grr::InputRate::Vertex),
+                    layout::vertex_buffer_view::<InstancePosition>(buffers.positions, grr::InputRate::Instance { divisor: 1 }),
+                    layout::vertex_buffer_view::<InstanceColor>(buffers.colors, grr::InputRate::Instance { divisor: 1 }),
+                ],
+            );
+            grr.bind_uniform_buffers(
+                0,
+                &[layout::uniform_range::<LocalsParticles>(u_locals)],
+            );
+            grr.bind_image_views(
+                0,
+                &[
+                    self.texture.as_view(),
+                ],
+            );
+            grr.bind_samplers(0, &[self.sampler]);
+            grr.draw(grr::Primitive::TriangleStrip, 0..4, 0..num_particles);
+
+            // end
+            grr.delete_buffer(u_locals);
+        }
+    }
+
+    pub fn destroy(&mut self, grr: &grr::Device) {
+        unsafe {
+            grr.delete_buffer(self.vertices);
+            grr.delete_image(self.texture);
+            grr.delete_sampler(self.sampler);
+            grr.delete_vertex_array(self.vertex_array);
+            grr.delete_pipeline(self.pipeline);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raster;
+
+    #[test]
+    fn capacity_is_the_shorter_slice_in_whole_particles() {
+        let mut positions = [0.0; 4 * 3 + 2];
+        let mut colors = [0.0; 4 * 5];
+        let writer = ParticleWriter::new(&mut positions, &mut colors);
+        assert_eq!(writer.capacity(), 3);
+        assert_eq!(writer.count(), 0);
+    }
+
+    #[test]
+    fn writes_past_capacity_are_rejected() {
+        let mut positions = [0.0; 8];
+        let mut colors = [0.0; 8];
+        let mut writer = ParticleWriter::new(&mut positions, &mut colors);
+        assert!(writer.set_position(1, [1.0; 4]));
+        assert!(writer.set_color(1, [2.0; 4]));
+        assert!(!writer.set_position(2, [3.0; 4]));
+        assert!(!writer.set_color(2, [4.0; 4]));
+        assert_eq!(writer.count(), 2);
+        assert_eq!(positions, [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(colors, [0.0, 0.0, 0.0, 0.0, 2.0, 2.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn count_is_one_past_the_highest_position() {
+        let mut positions = [0.0; 4 * 10];
+        let mut colors = [0.0; 4 * 10];
+        let mut writer = ParticleWriter::new(&mut positions, &mut colors);
+        writer.set_position(6, [0.0; 4]);
+        writer.set_position(2, [0.0; 4]);
+        assert_eq!(writer.count(), 7);
+        // colors alone don't make a particle live
+        writer.set_color(9, [0.0; 4]);
+        assert_eq!(writer.count(), 7);
+    }
+
+    #[test]
+    fn fill_clamps_to_capacity() {
+        let capacity = FILL_CHUNK * 2 + 5;
+        for &parallel in &[false, true] {
+            let mut positions = vec![0.0; capacity * 4];
+            let mut colors = vec![0.0; capacity * 4];
+            let mut writer = ParticleWriter::new(&mut positions, &mut colors);
+            writer.set_parallel(parallel);
+            writer.fill(capacity as u32 + 100, |first, run| {
+                for i in 0..run.capacity() as usize {
+                    run.set_position(i, [(first as usize + i) as f32; 4]);
+                }
+            });
+            assert_eq!(writer.count(), capacity as u32);
+            assert!(positions.chunks(4).enumerate().all(|(i, p)| p[0] == i as f32));
+        }
+    }
+
+    #[test]
+    fn count_resets_between_writes() {
+        let mut buffers = raster::ParticleBuffers::new(16);
+        buffers.write(|particles| particles.fill(12, |_, run| {
+            for i in 0..run.capacity() as usize {
+                run.set_position(i, [0.0; 4]);
+            }
+        }));
+        assert_eq!(buffers.count(), 12);
+        buffers.write(|particles| {
+            particles.set_position(3, [0.0; 4]);
+        });
+        assert_eq!(buffers.count(), 4);
+        buffers.write(|_| ());
+        assert_eq!(buffers.count(), 0);
+    }
+}
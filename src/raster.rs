@@ -0,0 +1,325 @@
+use anyhow::{Context, Result};
+use common::locals::{LocalsBackground, LocalsParticles};
+use flink::{f32x4, vec3, vec4};
+use shader::post::{linear_to_srgb, srgb_to_linear};
+use shader::{lerp, saturate};
+use std::path::Path;
+
+use crate::background;
+use crate::camera;
+use crate::effect;
+use crate::particles;
+
+// CPU reference for the GPU pipeline, runs the shading functions of the shader crate
+// with the rasterization, sampler and blend state of `background.rs` and `particles.rs`.
+// Keep it in step with them.
+//
+// Not modelled: MSAA, 8 bit quantization between blends, and near/far clipping
+// (quads with a vertex behind the camera are dropped).
+
+// linear rgba render target, like the 8 bit default framebuffer values stay in [0, 1]
+pub struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Frame {
+    // cleared to opaque black like the main loop
+    pub fn new(width: u32, height: u32) -> Self {
+        Frame {
+            width: width,
+            height: height,
+            pixels: vec![[0.0, 0.0, 0.0, 1.0]; (width * height) as usize],
+        }
+    }
+
+    // `SrcAlpha, One` additive blending on color and alpha
+    fn blend(&mut self, x: u32, y: u32, src: [f32; 4]) {
+        let dst = &mut self.pixels[(y * self.width + x) as usize];
+        for c in 0..4 {
+            dst[c] = saturate(dst[c] + src[c] * src[3]);
+        }
+    }
+
+    // rows top to bottom, `srgb` encodes like an sRGB framebuffer
+    pub fn to_image(&self, srgb: bool) -> image::RgbaImage {
+        let mut img = image::RgbaImage::new(self.width, self.height);
+        for (pixel, value) in img.pixels_mut().zip(self.pixels.iter()) {
+            let encode = |c: f32| if srgb { linear_to_srgb(c) } else { c };
+            *pixel = image::Rgba([
+                (encode(value[0]) * 255.0).round() as u8,
+                (encode(value[1]) * 255.0).round() as u8,
+                (encode(value[2]) * 255.0).round() as u8,
+                (value[3] * 255.0).round() as u8,
+            ]);
+        }
+        img
+    }
+}
+
+// RGBA texture as uploaded with `R8G8B8A8_SRGB`, rgb linearized on load
+struct Texture {
+    width: u32,
+    height: u32,
+    texels: Vec<[f32; 4]>,
+}
+
+impl Texture {
+    fn load(path: &Path) -> Result<Self> {
+        let img = image::open(path)
+            .with_context(|| format!("failed to open texture {}", path.display()))?
+            .to_rgba8();
+        let texels = img
+            .pixels()
+            .map(|p| {
+                [
+                    srgb_to_linear(p[0] as f32 / 255.0),
+                    srgb_to_linear(p[1] as f32 / 255.0),
+                    srgb_to_linear(p[2] as f32 / 255.0),
+                    p[3] as f32 / 255.0,
+                ]
+            })
+            .collect();
+        Ok(Texture {
+            width: img.width(),
+            height: img.height(),
+            texels: texels,
+        })
+    }
+
+    // `ClampBorder` with a transparent black border
+    fn fetch(&self, x: i32, y: i32) -> [f32; 4] {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return [0.0; 4];
+        }
+        self.texels[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    // bilinear on the base level, the sampler has no mip filter
+    fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let t00 = self.fetch(x0, y0);
+        let t10 = self.fetch(x0 + 1, y0);
+        let t01 = self.fetch(x0, y0 + 1);
+        let t11 = self.fetch(x0 + 1, y0 + 1);
+        let mut out = [0.0; 4];
+        for c in 0..4 {
+            out[c] = lerp(lerp(t00[c], t10[c], fx), lerp(t01[c], t11[c], fx), fy);
+        }
+        out
+    }
+}
+
+// CPU side of `particles::ParticleBuffers`, written through the same `ParticleWriter`
+pub struct ParticleBuffers {
+    positions: Vec<f32>,
+    colors: Vec<f32>,
+    count: u32,
+}
+
+impl ParticleBuffers {
+    pub fn new(capacity: u32) -> Self {
+        ParticleBuffers {
+            positions: vec![0.0; capacity as usize * 4],
+            colors: vec![0.0; capacity as usize * 4],
+            count: 0,
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn write<F: FnOnce(&mut particles::ParticleWriter)>(&mut self, f: F) {
+        let mut writer = particles::ParticleWriter::new(&mut self.positions, &mut self.colors);
+        f(&mut writer);
+        self.count = writer.count();
+    }
+
+    fn particle(&self, index: usize) -> (f32x4, f32x4) {
+        let p = &self.positions[index * 4..index * 4 + 4];
+        let c = &self.colors[index * 4..index * 4 + 4];
+        (vec4(p[0], p[1], p[2], p[3]), vec4(c[0], c[1], c[2], c[3]))
+    }
+}
+
+// `particles_vs` outputs
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: f32x4,
+    texcoord: [f32; 3],
+    color: f32x4,
+}
+
+// window position with the perspective divide, attributes kept for perspective correct interpolation
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    inv_w: f32,
+    attributes: [f32; 7],
+}
+
+fn particles_vs(locals: LocalsParticles, position: [f32; 3], posscale: f32x4, color: f32x4) -> Vertex {
+    let vertex = shader::particles::vertex(locals, vec3(position[0], position[1], position[2]), posscale);
+    Vertex {
+        position: vertex.position,
+        texcoord: [vertex.texcoord.x, vertex.texcoord.y, vertex.texcoord.z],
+        color: color,
+    }
+}
+
+fn particles_fs(texture: &Texture, texcoord: [f32; 3], color: [f32; 4]) -> [f32; 4] {
+    let tex = texture.sample(texcoord[0], texcoord[1]);
+    let out = shader::particles::color(
+        vec4(tex[0], tex[1], tex[2], tex[3]),
+        vec3(texcoord[0], texcoord[1], texcoord[2]),
+        vec4(color[0], color[1], color[2], color[3]),
+    );
+    [out.x, out.y, out.z, out.w]
+}
+
+pub struct Rasterizer {
+    texture: Texture,
+    parallel: bool,
+    // index into `shader::background::SKIES`
+    sky_variant: usize,
+}
+
+impl Rasterizer {
+    pub fn new() -> Result<Self> {
+        Ok(Rasterizer {
+            texture: Texture::load(Path::new("assets/particle.png"))?,
+            parallel: true,
+            sky_variant: 0,
+        })
+    }
+
+    // `false` keeps the particle updates on the calling thread
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    // see `background::find_variant`
+    pub fn set_sky_variant(&mut self, variant: usize) {
+        self.sky_variant = variant.min(shader::background::SKIES.len() - 1);
+    }
+
+    // one frame of `effect` at `time` over the selected sky variant
+    pub fn render(&self, camera: &camera::Camera, effect: &dyn effect::Effect, time: f32, width: u32, height: u32) -> Frame {
+        let mut buffers = ParticleBuffers::new(effect.capacity());
+        buffers.write(|particles| {
+            particles.set_parallel(self.parallel);
+            effect.update(particles, time);
+        });
+
+        let mut frame = Frame::new(width, height);
+        self.draw_background(&mut frame, camera, background::DEFAULT_SKY);
+        self.draw_particles(&mut frame, camera, &buffers, effect.dof());
+        frame
+    }
+
+    // fullscreen pass, `background_vs` unprojects every pixel center
+    pub fn draw_background(&self, frame: &mut Frame, camera: &camera::Camera, sky: [f32; 3]) {
+        let locals = LocalsBackground {
+            view_world: camera.world_view(),
+            proj_view: camera.view_proj_inv(),
+            sky: vec4(sky[0], sky[1], sky[2], 1.0),
+        };
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let ndc_x = (x as f32 + 0.5) / frame.width as f32 * 2.0 - 1.0;
+                let ndc_y = 1.0 - (y as f32 + 0.5) / frame.height as f32 * 2.0;
+                let view_dir = shader::background::view_dir(locals, vec4(ndc_x, ndc_y, 0.0, 1.0));
+                let color = shader::background::color(self.sky_variant, locals, view_dir);
+                frame.blend(x, y, [color.x, color.y, color.z, color.w]);
+            }
+        }
+    }
+
+    pub fn draw_particles(&self, frame: &mut Frame, camera: &camera::Camera, buffers: &ParticleBuffers, dof: particles::Dof) {
+        let locals = LocalsParticles::new(camera.world_view_inv(), camera.view_proj(), dof.depth, dof.apperture);
+
+        // instances in order, additive blending doesn't depend on it but the clamping does
+        for instance in 0..buffers.count() as usize {
+            let (posscale, color) = buffers.particle(instance);
+            let mut vertices = [None; 4];
+            for (i, corner) in particles::QUAD.iter().enumerate() {
+                let vertex = particles_vs(locals, corner.position, posscale, color);
+                vertices[i] = self.to_screen(frame, vertex);
+            }
+            if let [Some(v0), Some(v1), Some(v2), Some(v3)] = vertices {
+                self.draw_triangle(frame, v0, v1, v2);
+                self.draw_triangle(frame, v2, v1, v3);
+            }
+        }
+    }
+
+    fn to_screen(&self, frame: &Frame, vertex: Vertex) -> Option<ScreenVertex> {
+        let w = vertex.position.w;
+        if w <= 0.0 {
+            return None;
+        }
+        let inv_w = 1.0 / w;
+        let c = vertex.color;
+        let t = vertex.texcoord;
+        Some(ScreenVertex {
+            x: (vertex.position.x * inv_w * 0.5 + 0.5) * frame.width as f32,
+            y: (0.5 - vertex.position.y * inv_w * 0.5) * frame.height as f32,
+            inv_w: inv_w,
+            attributes: [t[0] * inv_w, t[1] * inv_w, t[2] * inv_w, c.x * inv_w, c.y * inv_w, c.z * inv_w, c.w * inv_w],
+        })
+    }
+
+    fn draw_triangle(&self, frame: &mut Frame, v0: ScreenVertex, v1: ScreenVertex, v2: ScreenVertex) {
+        let edge = |a: &ScreenVertex, b: &ScreenVertex, x: f32, y: f32| (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x);
+
+        // no culling, flip to a positive area
+        let (v1, v2) = if edge(&v0, &v1, v2.x, v2.y) < 0.0 { (v2, v1) } else { (v1, v2) };
+        let area = edge(&v0, &v1, v2.x, v2.y);
+        if area <= 0.0 {
+            return;
+        }
+
+        // pixels exactly on an edge shared by two triangles belong to one of them
+        let owns = |a: &ScreenVertex, b: &ScreenVertex| {
+            let dy = b.y - a.y;
+            dy > 0.0 || (dy == 0.0 && b.x < a.x)
+        };
+        let owns_edges = [owns(&v1, &v2), owns(&v2, &v0), owns(&v0, &v1)];
+
+        let min_x = v0.x.min(v1.x).min(v2.x).floor().max(0.0) as u32;
+        let min_y = v0.y.min(v1.y).min(v2.y).floor().max(0.0) as u32;
+        let max_x = (v0.x.max(v1.x).max(v2.x).ceil().max(0.0) as u32).min(frame.width);
+        let max_y = (v0.y.max(v1.y).max(v2.y).ceil().max(0.0) as u32).min(frame.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+                let weights = [edge(&v1, &v2, px, py), edge(&v2, &v0, px, py), edge(&v0, &v1, px, py)];
+                let inside = weights.iter().zip(owns_edges.iter()).all(|(w, owns)| *w > 0.0 || (*w == 0.0 && *owns));
+                if !inside {
+                    continue;
+                }
+
+                let b = [weights[0] / area, weights[1] / area, weights[2] / area];
+                let inv_w = b[0] * v0.inv_w + b[1] * v1.inv_w + b[2] * v2.inv_w;
+                let mut a = [0.0; 7];
+                for i in 0..7 {
+                    a[i] = (b[0] * v0.attributes[i] + b[1] * v1.attributes[i] + b[2] * v2.attributes[i]) / inv_w;
+                }
+                let color = particles_fs(&self.texture, [a[0], a[1], a[2]], [a[3], a[4], a[5], a[6]]);
+                frame.blend(x, y, color);
+            }
+        }
+    }
+}
@@ -0,0 +1,856 @@
+use anyhow::{anyhow, bail, Result};
+use common::layout::align_up;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::background;
+use crate::layout::PipelineDesc;
+use crate::particles;
+
+// Reads just enough of a SPIR-V module to list the interface of its entry points: inputs and
+// outputs with their locations, uniform blocks with their member offsets and samplers, and
+// checks them against the `PipelineDesc` the host builds its pipelines from.
+
+const MAGIC: u32 = 0x0723_0203;
+
+mod op {
+    pub const NAME: u32 = 5;
+    pub const MEMBER_NAME: u32 = 6;
+    pub const ENTRY_POINT: u32 = 15;
+    pub const TYPE_BOOL: u32 = 20;
+    pub const TYPE_INT: u32 = 21;
+    pub const TYPE_FLOAT: u32 = 22;
+    pub const TYPE_VECTOR: u32 = 23;
+    pub const TYPE_MATRIX: u32 = 24;
+    pub const TYPE_IMAGE: u32 = 25;
+    pub const TYPE_SAMPLER: u32 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u32 = 27;
+    pub const TYPE_ARRAY: u32 = 28;
+    pub const TYPE_STRUCT: u32 = 30;
+    pub const TYPE_POINTER: u32 = 32;
+    pub const CONSTANT: u32 = 43;
+    pub const FUNCTION: u32 = 54;
+    pub const FUNCTION_END: u32 = 56;
+    pub const FUNCTION_CALL: u32 = 57;
+    pub const VARIABLE: u32 = 59;
+    pub const LOAD: u32 = 61;
+    pub const STORE: u32 = 62;
+    pub const COPY_MEMORY: u32 = 63;
+    pub const ACCESS_CHAIN: u32 = 65;
+    pub const IN_BOUNDS_ACCESS_CHAIN: u32 = 66;
+    pub const DECORATE: u32 = 71;
+    pub const MEMBER_DECORATE: u32 = 72;
+}
+
+mod decoration {
+    pub const BUILT_IN: u32 = 11;
+    pub const LOCATION: u32 = 30;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+mod storage {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+    Other(u32),
+}
+
+impl Stage {
+    fn from_model(model: u32) -> Self {
+        match model {
+            0 => Stage::Vertex,
+            4 => Stage::Fragment,
+            other => Stage::Other(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: Box<Type>, count: u32 },
+    Matrix { column: Box<Type>, count: u32 },
+    Image,
+    Sampler,
+    SampledImage,
+    Array { element: Box<Type>, length: Option<u32> },
+    Struct { name: Option<String>, members: Vec<Member> },
+    Unknown(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: Option<String>,
+    pub offset: Option<u32>,
+    pub ty: Type,
+}
+
+impl Type {
+    // number of scalars of an interface variable, `None` for anything but scalars and vectors
+    pub fn components(&self) -> Option<u32> {
+        match self {
+            Type::Bool | Type::Int { .. } | Type::Float { .. } => Some(1),
+            Type::Vector { count, .. } => Some(*count),
+            _ => None,
+        }
+    }
+
+    // bytes taken inside a std140 block, structs are padded to 16 like `Std140::SIZE`
+    pub fn std140_size(&self) -> Option<usize> {
+        match self {
+            Type::Int { width, .. } | Type::Float { width } => Some(*width as usize / 8),
+            Type::Vector { component, count } => Some(component.std140_size()? * *count as usize),
+            Type::Matrix { count, .. } => Some(16 * *count as usize),
+            Type::Array { element, length } => Some(align_up(element.std140_size()?, 16) * (*length)? as usize),
+            Type::Struct { members, .. } => match members.last() {
+                Some(last) => Some(align_up(last.offset? as usize + last.ty.std140_size()?, 16)),
+                None => Some(0),
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::Int { width, signed: true } => write!(f, "i{}", width),
+            Type::Int { width, signed: false } => write!(f, "u{}", width),
+            Type::Float { width } => write!(f, "f{}", width),
+            Type::Vector { component, count } => write!(f, "{}x{}", component, count),
+            Type::Matrix { column, count } => write!(f, "{}x{}", column, count),
+            Type::Image => write!(f, "image"),
+            Type::Sampler => write!(f, "sampler"),
+            Type::SampledImage => write!(f, "sampled image"),
+            Type::Array { element, length: Some(length) } => write!(f, "[{}; {}]", element, length),
+            Type::Array { element, length: None } => write!(f, "[{}]", element),
+            Type::Struct { name, .. } => write!(f, "struct {}", name.as_deref().unwrap_or("_")),
+            Type::Unknown(opcode) => write!(f, "<op {}>", opcode),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: Option<String>,
+    pub location: Option<u32>,
+    pub builtin: Option<u32>,
+    pub binding: Option<u32>,
+    pub set: Option<u32>,
+    pub ty: Type,
+}
+
+impl Variable {
+    fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or("_")
+    }
+}
+
+fn or_unknown(value: Option<u32>) -> String {
+    value.map_or("?".to_string(), |value| value.to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryPoint {
+    pub name: String,
+    pub stage: Stage,
+    pub inputs: Vec<Variable>,
+    pub outputs: Vec<Variable>,
+    // uniform blocks
+    pub uniforms: Vec<Variable>,
+    // images and samplers
+    pub samplers: Vec<Variable>,
+}
+
+impl EntryPoint {
+    fn input(&self, location: u32) -> Option<&Variable> {
+        self.inputs.iter().find(|var| var.location == Some(location))
+    }
+
+    fn output(&self, location: u32) -> Option<&Variable> {
+        self.outputs.iter().find(|var| var.location == Some(location))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub entry_points: Vec<EntryPoint>,
+}
+
+#[derive(Default)]
+struct Function {
+    // global variables accessed directly
+    globals: HashSet<u32>,
+    calls: HashSet<u32>,
+}
+
+#[derive(Default)]
+struct Parser {
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    decorations: HashMap<(u32, u32), u32>,
+    member_offsets: HashMap<(u32, u32), u32>,
+    // raw type instructions by result id
+    types: HashMap<u32, (u32, Vec<u32>)>,
+    constants: HashMap<u32, u32>,
+    // pointer type id to (storage class, pointee type id)
+    pointers: HashMap<u32, (u32, u32)>,
+    // global variable id to pointer type id
+    globals: HashMap<u32, u32>,
+    functions: HashMap<u32, Function>,
+    // (execution model, function, name, interface)
+    entry_points: Vec<(u32, u32, String, Vec<u32>)>,
+}
+
+// nul terminated UTF-8 packed into words, returns the string and the words it took
+fn string(words: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        for byte in word.to_le_bytes().iter() {
+            if *byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1);
+            }
+            bytes.push(*byte);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+impl Parser {
+    fn instruction(&mut self, opcode: u32, operands: &[u32], function: &mut Option<u32>) -> Result<()> {
+        let operand = |i: usize| operands.get(i).copied().ok_or_else(|| anyhow!("truncated instruction (opcode {})", opcode));
+
+        match opcode {
+            op::NAME => {
+                self.names.insert(operand(0)?, string(&operands[1..]).0);
+            }
+            op::MEMBER_NAME => {
+                self.member_names.insert((operand(0)?, operand(1)?), string(&operands[2..]).0);
+            }
+            op::ENTRY_POINT => {
+                let (name, len) = string(operands.get(2..).unwrap_or(&[]));
+                let interface = operands.get(2 + len..).unwrap_or(&[]).to_vec();
+                self.entry_points.push((operand(0)?, operand(1)?, name, interface));
+            }
+            op::DECORATE => {
+                let value = operands.get(2).copied().unwrap_or(0);
+                self.decorations.insert((operand(0)?, operand(1)?), value);
+            }
+            op::MEMBER_DECORATE => {
+                if operand(2)? == decoration::OFFSET {
+                    self.member_offsets.insert((operand(0)?, operand(1)?), operand(3)?);
+                }
+            }
+            op::TYPE_BOOL..=op::TYPE_STRUCT => {
+                self.types.insert(operand(0)?, (opcode, operands[1..].to_vec()));
+            }
+            op::TYPE_POINTER => {
+                self.pointers.insert(operand(0)?, (operand(1)?, operand(2)?));
+            }
+            op::CONSTANT => {
+                self.constants.insert(operand(1)?, operand(2)?);
+            }
+            op::VARIABLE if function.is_none() => {
+                self.globals.insert(operand(1)?, operand(0)?);
+            }
+            op::FUNCTION => {
+                *function = Some(operand(1)?);
+            }
+            op::FUNCTION_END => {
+                *function = None;
+            }
+            _ => {}
+        }
+
+        // accesses to globals and calls inside function bodies
+        if let Some(id) = *function {
+            let accessed = match opcode {
+                op::LOAD | op::ACCESS_CHAIN | op::IN_BOUNDS_ACCESS_CHAIN => operands.get(2..3),
+                op::STORE => operands.get(..1),
+                op::COPY_MEMORY => operands.get(..2),
+                // pointers passed as arguments
+                op::FUNCTION_CALL => operands.get(3..),
+                _ => None,
+            }
+            .unwrap_or(&[]);
+            let globals = &self.globals;
+            let entry = self.functions.entry(id).or_insert_with(Function::default);
+            entry.globals.extend(accessed.iter().filter(|id| globals.contains_key(id)));
+            if opcode == op::FUNCTION_CALL {
+                entry.calls.insert(operand(2)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations.get(&(id, decoration)).copied()
+    }
+
+    fn ty(&self, id: u32, depth: u32) -> Result<Type> {
+        if depth > 32 {
+            bail!("type %{} nests too deep", id);
+        }
+        let (opcode, operands) = match self.types.get(&id) {
+            Some(ty) => ty,
+            None => bail!("unknown type %{}", id),
+        };
+        let operand = |i: usize| operands.get(i).copied().ok_or_else(|| anyhow!("truncated type %{}", id));
+
+        Ok(match *opcode {
+            op::TYPE_BOOL => Type::Bool,
+            op::TYPE_INT => Type::Int { width: operand(0)?, signed: operand(1)? != 0 },
+            op::TYPE_FLOAT => Type::Float { width: operand(0)? },
+            op::TYPE_VECTOR => Type::Vector { component: Box::new(self.ty(operand(0)?, depth + 1)?), count: operand(1)? },
+            op::TYPE_MATRIX => Type::Matrix { column: Box::new(self.ty(operand(0)?, depth + 1)?), count: operand(1)? },
+            op::TYPE_IMAGE => Type::Image,
+            op::TYPE_SAMPLER => Type::Sampler,
+            op::TYPE_SAMPLED_IMAGE => Type::SampledImage,
+            op::TYPE_ARRAY => Type::Array {
+                element: Box::new(self.ty(operand(0)?, depth + 1)?),
+                length: self.constants.get(&operand(1)?).copied(),
+            },
+            op::TYPE_STRUCT => Type::Struct {
+                name: self.names.get(&id).cloned(),
+                members: operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, member)| {
+                        Ok(Member {
+                            name: self.member_names.get(&(id, i as u32)).cloned(),
+                            offset: self.member_offsets.get(&(id, i as u32)).copied(),
+                            ty: self.ty(*member, depth + 1)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            },
+            other => Type::Unknown(other),
+        })
+    }
+
+    // globals reachable from `function` through calls
+    fn reachable(&self, function: u32) -> HashSet<u32> {
+        let mut globals = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![function];
+        while let Some(id) = pending.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            if let Some(function) = self.functions.get(&id) {
+                globals.extend(function.globals.iter().copied());
+                pending.extend(function.calls.iter().copied());
+            }
+        }
+        globals
+    }
+
+    fn entry_point(&self, model: u32, function: u32, name: &str, interface: &[u32]) -> Result<EntryPoint> {
+        let mut entry = EntryPoint {
+            name: name.to_string(),
+            stage: Stage::from_model(model),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            uniforms: Vec::new(),
+            samplers: Vec::new(),
+        };
+
+        // the interface lists inputs and outputs (and since SPIR-V 1.4 every global), older
+        // modules only reference resources from the function bodies
+        let mut ids: Vec<u32> = interface.iter().copied().chain(self.reachable(function)).collect();
+        ids.sort();
+        ids.dedup();
+
+        for id in ids {
+            let pointer = match self.globals.get(&id) {
+                Some(pointer) => *pointer,
+                None => continue,
+            };
+            let (class, pointee) = match self.pointers.get(&pointer) {
+                Some(pointer) => *pointer,
+                None => bail!("variable %{} is not a pointer", id),
+            };
+            let var = Variable {
+                name: self.names.get(&id).cloned(),
+                location: self.decoration(id, decoration::LOCATION),
+                builtin: self.decoration(id, decoration::BUILT_IN),
+                binding: self.decoration(id, decoration::BINDING),
+                set: self.decoration(id, decoration::DESCRIPTOR_SET),
+                ty: self.ty(pointee, 0)?,
+            };
+            match class {
+                storage::INPUT => entry.inputs.push(var),
+                storage::OUTPUT => entry.outputs.push(var),
+                storage::UNIFORM => entry.uniforms.push(var),
+                storage::UNIFORM_CONSTANT => entry.samplers.push(var),
+                _ => {}
+            }
+        }
+
+        entry.inputs.sort_by_key(|var| (var.location.is_none(), var.location, var.builtin));
+        entry.outputs.sort_by_key(|var| (var.location.is_none(), var.location, var.builtin));
+        entry.uniforms.sort_by_key(|var| (var.set, var.binding));
+        entry.samplers.sort_by_key(|var| (var.set, var.binding));
+        Ok(entry)
+    }
+}
+
+impl Module {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() % 4 != 0 || bytes.len() < 20 {
+            bail!("not a SPIR-V module, {} bytes", bytes.len());
+        }
+        let mut words: Vec<u32> = bytes
+            .chunks(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        if words[0] == MAGIC.swap_bytes() {
+            words.iter_mut().for_each(|word| *word = word.swap_bytes());
+        } else if words[0] != MAGIC {
+            bail!("not a SPIR-V module, magic {:#010x}", words[0]);
+        }
+
+        let mut parser = Parser::default();
+        let mut function = None;
+        let mut offset = 5;
+        while offset < words.len() {
+            let count = (words[offset] >> 16) as usize;
+            let opcode = words[offset] & 0xffff;
+            if count == 0 || offset + count > words.len() {
+                bail!("malformed instruction at word {}", offset);
+            }
+            parser.instruction(opcode, &words[offset + 1..offset + count], &mut function)?;
+            offset += count;
+        }
+
+        let entry_points = parser
+            .entry_points
+            .iter()
+            .map(|(model, function, name, interface)| parser.entry_point(*model, *function, name, interface))
+            .collect::<Result<_>>()?;
+        Ok(Module { entry_points: entry_points })
+    }
+
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPoint> {
+        self.entry_points.iter().find(|entry| entry.name == name)
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entry_points {
+            writeln!(f, "{} ({:?})", entry.name, entry.stage)?;
+            for (kind, vars) in &[("in", &entry.inputs), ("out", &entry.outputs)] {
+                for var in vars.iter() {
+                    match (var.location, var.builtin) {
+                        (Some(location), _) => write!(f, "  {:<4} location {:<2}", kind, location)?,
+                        (None, Some(builtin)) => write!(f, "  {:<4} builtin {:<3}", kind, builtin)?,
+                        (None, None) => write!(f, "  {:<4} {:<11}", kind, "")?,
+                    }
+                    writeln!(f, " {:<16} {}", var.label(), var.ty)?;
+                }
+            }
+            for var in &entry.samplers {
+                writeln!(f, "  {:<4} binding {:<3} {:<16} {}", "tex", var.binding.unwrap_or(0), var.label(), var.ty)?;
+            }
+            for var in &entry.uniforms {
+                let size = or_unknown(var.ty.std140_size().map(|size| size as u32));
+                writeln!(f, "  {:<4} binding {:<3} {:<16} {}, {} bytes", "ubo", var.binding.unwrap_or(0), var.label(), var.ty, size)?;
+                if let Type::Struct { members, .. } = &var.ty {
+                    for (i, member) in members.iter().enumerate() {
+                        let offset = or_unknown(member.offset);
+                        let name = member.name.clone().unwrap_or_else(|| i.to_string());
+                        writeln!(f, "       {:>4} {:<16} {}", offset, name, member.ty)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// every pipeline the host creates from `spirv::SHADER`
+pub fn host_pipelines() -> Vec<PipelineDesc> {
+    let mut pipelines = background::pipelines();
+    pipelines.push(particles::PIPELINE);
+    pipelines
+}
+
+// mismatches between `desc` and the entry points it names, empty if they agree
+pub fn validate(module: &Module, desc: &PipelineDesc) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    let stage = |name: &str, stage: Stage, errors: &mut Vec<String>| match module.entry_point(name) {
+        Some(entry) if entry.stage == stage => Some(entry),
+        Some(entry) => {
+            errors.push(format!("entry point `{}` is a {:?} shader, expected {:?}", name, entry.stage, stage));
+            None
+        }
+        None => {
+            errors.push(format!("no entry point `{}`", name));
+            None
+        }
+    };
+    let vs = stage(desc.vertex, Stage::Vertex, &mut errors);
+    let fs = stage(desc.fragment, Stage::Fragment, &mut errors);
+    let (vs, fs) = match (vs, fs) {
+        (Some(vs), Some(fs)) => (vs, fs),
+        _ => return errors,
+    };
+
+    // vertex buffers against vertex shader inputs
+    for (binding, attributes) in desc.vertex_buffers.iter().enumerate() {
+        for attribute in attributes.iter() {
+            match vs.input(attribute.location) {
+                Some(input) if input.ty.components() == Some(attribute.format.components()) => {}
+                Some(input) => errors.push(format!(
+                    "vertex attribute location {} (binding {}) is {:?}, `{}` reads {}",
+                    attribute.location, binding, attribute.format, vs.name, input.ty
+                )),
+                None => errors.push(format!(
+                    "vertex attribute location {} (binding {}) is not read by `{}`",
+                    attribute.location, binding, vs.name
+                )),
+            }
+        }
+    }
+    for input in vs.inputs.iter().filter(|input| input.builtin.is_none()) {
+        let provided = desc
+            .vertex_buffers
+            .iter()
+            .any(|attributes| attributes.iter().any(|attribute| Some(attribute.location) == input.location));
+        if !provided {
+            errors.push(format!("`{}` input `{}` at location {} has no vertex attribute", vs.name, input.label(), or_unknown(input.location)));
+        }
+    }
+
+    // vertex outputs against fragment inputs
+    for input in fs.inputs.iter().filter(|input| input.builtin.is_none()) {
+        match input.location.and_then(|location| vs.output(location)) {
+            Some(output) if output.ty == input.ty => {}
+            Some(output) => errors.push(format!(
+                "location {}: `{}` writes {}, `{}` reads {}",
+                or_unknown(input.location), vs.name, output.ty, fs.name, input.ty
+            )),
+            None => errors.push(format!("`{}` input `{}` at location {} is not written by `{}`", fs.name, input.label(), or_unknown(input.location), vs.name)),
+        }
+    }
+
+    // uniform blocks, every one the shaders use has to be bound with the same layout
+    let mut used = HashSet::new();
+    for entry in &[vs, fs] {
+        for block in &entry.uniforms {
+            let binding = block.binding.unwrap_or(0);
+            used.insert(binding);
+            let uniform = match desc.uniforms.iter().find(|uniform| uniform.binding == binding) {
+                Some(uniform) => uniform,
+                None => {
+                    errors.push(format!("`{}` uniform `{}` at binding {} is not bound", entry.name, block.label(), binding));
+                    continue;
+                }
+            };
+            let members = match &block.ty {
+                Type::Struct { members, .. } => members,
+                ty => {
+                    errors.push(format!("`{}` uniform at binding {} is {}, expected a block", entry.name, binding, ty));
+                    continue;
+                }
+            };
+            if members.len() != uniform.fields.len() {
+                errors.push(format!(
+                    "`{}` uniform at binding {} has {} members, the host block has {} fields",
+                    entry.name, binding, members.len(), uniform.fields.len()
+                ));
+            }
+            for (member, field) in members.iter().zip(uniform.fields.iter()) {
+                if member.offset != Some(field.offset as u32) {
+                    errors.push(format!(
+                        "`{}` uniform at binding {}: `{}` is at offset {} on the host, {} in the shader",
+                        entry.name, binding, field.name, field.offset, or_unknown(member.offset)
+                    ));
+                }
+            }
+            if let Some(size) = block.ty.std140_size() {
+                if size > uniform.size {
+                    errors.push(format!("`{}` uniform at binding {} needs {} bytes, the host binds {}", entry.name, binding, size, uniform.size));
+                }
+            }
+        }
+    }
+    for uniform in desc.uniforms {
+        if !used.contains(&uniform.binding) {
+            errors.push(format!("uniform binding {} is not used by `{}` or `{}`", uniform.binding, vs.name, fs.name));
+        }
+    }
+
+    // samplers
+    let mut used = HashSet::new();
+    for entry in &[vs, fs] {
+        for sampler in &entry.samplers {
+            let binding = sampler.binding.unwrap_or(0);
+            used.insert(binding);
+            if !desc.samplers.contains(&binding) {
+                errors.push(format!("`{}` texture `{}` at binding {} is not bound", entry.name, sampler.label(), binding));
+            }
+        }
+    }
+    for binding in desc.samplers {
+        if !used.contains(binding) {
+            errors.push(format!("sampler binding {} is not used by `{}` or `{}`", binding, vs.name, fs.name));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::UniformDesc;
+    use common::layout::{BlockField, VertexAttribute, VertexFormat};
+
+    const TYPE_FLOAT: u32 = 1;
+    const TYPE_VEC4: u32 = 2;
+    const TYPE_BLOCK: u32 = 3;
+    const PTR_INPUT: u32 = 4;
+    const PTR_OUTPUT: u32 = 5;
+    const PTR_UNIFORM: u32 = 6;
+    const VS_POSITION: u32 = 10;
+    const VS_COLOR: u32 = 11;
+    const FS_COLOR: u32 = 12;
+    const FS_TARGET: u32 = 13;
+    const GLOBALS: u32 = 14;
+    const UNUSED: u32 = 15;
+    const VS_MAIN: u32 = 20;
+    const FS_MAIN: u32 = 21;
+    const HELPER: u32 = 22;
+
+    // instruction words with the word count in the high half
+    fn instruction(words: &mut Vec<u32>, opcode: u32, operands: &[u32]) {
+        words.push(((operands.len() as u32 + 1) << 16) | opcode);
+        words.extend_from_slice(operands);
+    }
+
+    fn literal(s: &str) -> Vec<u32> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize(s.len() / 4 * 4 + 4, 0);
+        bytes.chunks(4).map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])).collect()
+    }
+
+    fn with_literal(head: &[u32], s: &str, tail: &[u32]) -> Vec<u32> {
+        head.iter().copied().chain(literal(s)).chain(tail.iter().copied()).collect()
+    }
+
+    // a vertex shader reading a uniform block through a helper function and a fragment
+    // shader passing its input through
+    fn module() -> Vec<u32> {
+        let mut words = vec![MAGIC, 0x0001_0000, 0, 100, 0];
+        let w = &mut words;
+        instruction(w, op::ENTRY_POINT, &with_literal(&[0, VS_MAIN], "vs", &[VS_POSITION, VS_COLOR]));
+        instruction(w, op::ENTRY_POINT, &with_literal(&[4, FS_MAIN], "fs", &[FS_COLOR, FS_TARGET]));
+        instruction(w, op::NAME, &with_literal(&[VS_POSITION], "position", &[]));
+        instruction(w, op::NAME, &with_literal(&[GLOBALS], "globals", &[]));
+        instruction(w, op::NAME, &with_literal(&[TYPE_BLOCK], "Globals", &[]));
+        instruction(w, op::MEMBER_NAME, &with_literal(&[TYPE_BLOCK, 0], "time", &[]));
+        instruction(w, op::MEMBER_NAME, &with_literal(&[TYPE_BLOCK, 1], "tint", &[]));
+        instruction(w, op::DECORATE, &[VS_POSITION, decoration::LOCATION, 0]);
+        instruction(w, op::DECORATE, &[VS_COLOR, decoration::LOCATION, 1]);
+        instruction(w, op::DECORATE, &[FS_COLOR, decoration::LOCATION, 1]);
+        instruction(w, op::DECORATE, &[FS_TARGET, decoration::LOCATION, 0]);
+        instruction(w, op::DECORATE, &[GLOBALS, decoration::DESCRIPTOR_SET, 0]);
+        instruction(w, op::DECORATE, &[GLOBALS, decoration::BINDING, 2]);
+        instruction(w, op::DECORATE, &[UNUSED, decoration::BINDING, 3]);
+        instruction(w, op::MEMBER_DECORATE, &[TYPE_BLOCK, 0, decoration::OFFSET, 0]);
+        instruction(w, op::MEMBER_DECORATE, &[TYPE_BLOCK, 1, decoration::OFFSET, 16]);
+        instruction(w, op::TYPE_FLOAT, &[TYPE_FLOAT, 32]);
+        instruction(w, op::TYPE_VECTOR, &[TYPE_VEC4, TYPE_FLOAT, 4]);
+        instruction(w, op::TYPE_STRUCT, &[TYPE_BLOCK, TYPE_FLOAT, TYPE_VEC4]);
+        instruction(w, op::TYPE_POINTER, &[PTR_INPUT, storage::INPUT, TYPE_VEC4]);
+        instruction(w, op::TYPE_POINTER, &[PTR_OUTPUT, storage::OUTPUT, TYPE_VEC4]);
+        instruction(w, op::TYPE_POINTER, &[PTR_UNIFORM, storage::UNIFORM, TYPE_BLOCK]);
+        instruction(w, op::VARIABLE, &[PTR_INPUT, VS_POSITION, storage::INPUT]);
+        instruction(w, op::VARIABLE, &[PTR_OUTPUT, VS_COLOR, storage::OUTPUT]);
+        instruction(w, op::VARIABLE, &[PTR_INPUT, FS_COLOR, storage::INPUT]);
+        instruction(w, op::VARIABLE, &[PTR_OUTPUT, FS_TARGET, storage::OUTPUT]);
+        instruction(w, op::VARIABLE, &[PTR_UNIFORM, GLOBALS, storage::UNIFORM]);
+        instruction(w, op::VARIABLE, &[PTR_UNIFORM, UNUSED, storage::UNIFORM]);
+        instruction(w, op::FUNCTION, &[0, VS_MAIN, 0, 0]);
+        instruction(w, op::FUNCTION_CALL, &[0, 30, HELPER]);
+        instruction(w, op::FUNCTION_END, &[]);
+        instruction(w, op::FUNCTION, &[0, HELPER, 0, 0]);
+        instruction(w, op::LOAD, &[TYPE_BLOCK, 31, GLOBALS]);
+        instruction(w, op::FUNCTION_END, &[]);
+        instruction(w, op::FUNCTION, &[0, FS_MAIN, 0, 0]);
+        instruction(w, op::FUNCTION_END, &[]);
+        words
+    }
+
+    fn bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+    }
+
+    fn vec4() -> Type {
+        Type::Vector { component: Box::new(Type::Float { width: 32 }), count: 4 }
+    }
+
+    const FIELDS: &[BlockField] = &[BlockField { name: "time", offset: 0 }, BlockField { name: "tint", offset: 16 }];
+    const ATTRIBUTES: &[VertexAttribute] = &[VertexAttribute { location: 0, format: VertexFormat::Xyzw32Float, offset: 0 }];
+    const UNIFORMS: &[UniformDesc] = &[UniformDesc { binding: 2, size: 32, fields: FIELDS }];
+
+    fn desc() -> PipelineDesc {
+        PipelineDesc {
+            name: "test",
+            vertex: "vs",
+            fragment: "fs",
+            vertex_buffers: &[ATTRIBUTES],
+            uniforms: UNIFORMS,
+            samplers: &[],
+        }
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut words = module();
+        words[0] = 0xdead_beef;
+        assert!(Module::parse(&bytes(&words)).is_err());
+        assert!(Module::parse(&bytes(&words[..4])).is_err());
+        assert!(Module::parse(&bytes(&module())[..41]).is_err());
+    }
+
+    #[test]
+    fn byte_swapped() {
+        let swapped: Vec<u8> = module().iter().flat_map(|word| word.to_be_bytes().to_vec()).collect();
+        let module = Module::parse(&swapped).unwrap();
+        assert_eq!(module.entry_points.len(), 2);
+        assert_eq!(module.entry_point("vs").unwrap().inputs[0].name.as_deref(), Some("position"));
+    }
+
+    #[test]
+    fn malformed_instructions() {
+        // word count 0
+        let mut words = module();
+        words.push(op::FUNCTION_END);
+        assert!(Module::parse(&bytes(&words)).is_err());
+
+        // word count past the end of the module
+        let mut words = module();
+        words.push((3 << 16) | op::DECORATE);
+        words.push(VS_POSITION);
+        assert!(Module::parse(&bytes(&words)).is_err());
+
+        // operands missing
+        let mut words = module();
+        instruction(&mut words, op::TYPE_POINTER, &[PTR_INPUT]);
+        assert!(Module::parse(&bytes(&words)).is_err());
+    }
+
+    #[test]
+    fn short_instructions_dont_panic() {
+        // every instruction of the module with one operand less, they may parse but mustn't panic
+        let words = module();
+        let mut offset = 5;
+        while offset < words.len() {
+            let count = words[offset] >> 16;
+            if count == 1 {
+                offset += 1;
+                continue;
+            }
+            let mut short = words[..offset].to_vec();
+            short.push(((count - 1) << 16) | (words[offset] & 0xffff));
+            short.extend_from_slice(&words[offset + 1..offset + count as usize - 1]);
+            let _ = Module::parse(&bytes(&short));
+            offset += count as usize;
+        }
+    }
+
+    #[test]
+    fn interface() {
+        let module = Module::parse(&bytes(&module())).unwrap();
+        let vs = module.entry_point("vs").unwrap();
+        assert_eq!(vs.stage, Stage::Vertex);
+        assert_eq!(vs.inputs.len(), 1);
+        assert_eq!(vs.inputs[0].location, Some(0));
+        assert_eq!(vs.inputs[0].ty, vec4());
+        assert_eq!(vs.outputs.len(), 1);
+        assert_eq!(vs.outputs[0].location, Some(1));
+
+        // the block is only read by a function `vs` calls, the unused one is not reachable
+        assert_eq!(vs.uniforms.len(), 1);
+        let globals = &vs.uniforms[0];
+        assert_eq!(globals.name.as_deref(), Some("globals"));
+        assert_eq!((globals.set, globals.binding), (Some(0), Some(2)));
+        match &globals.ty {
+            Type::Struct { name, members } => {
+                assert_eq!(name.as_deref(), Some("Globals"));
+                let offsets: Vec<_> = members.iter().map(|member| (member.name.as_deref(), member.offset)).collect();
+                assert_eq!(offsets, vec![(Some("time"), Some(0)), (Some("tint"), Some(16))]);
+            }
+            ty => panic!("expected a block, got {}", ty),
+        }
+        assert_eq!(globals.ty.std140_size(), Some(32));
+
+        let fs = module.entry_point("fs").unwrap();
+        assert_eq!(fs.stage, Stage::Fragment);
+        assert_eq!(fs.inputs[0].location, Some(1));
+        assert_eq!(fs.outputs[0].location, Some(0));
+        assert!(fs.uniforms.is_empty());
+    }
+
+    #[test]
+    fn validate_matching() {
+        let module = Module::parse(&bytes(&module())).unwrap();
+        assert_eq!(validate(&module, &desc()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn validate_location() {
+        const MOVED: &[VertexAttribute] = &[VertexAttribute { location: 3, format: VertexFormat::Xyzw32Float, offset: 0 }];
+        let module = Module::parse(&bytes(&module())).unwrap();
+        let desc = PipelineDesc { vertex_buffers: &[MOVED], ..desc() };
+        assert_eq!(
+            validate(&module, &desc),
+            vec![
+                "vertex attribute location 3 (binding 0) is not read by `vs`".to_string(),
+                "`vs` input `position` at location 0 has no vertex attribute".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_binding() {
+        const MOVED: &[UniformDesc] = &[UniformDesc { binding: 1, size: 32, fields: FIELDS }];
+        let module = Module::parse(&bytes(&module())).unwrap();
+        let desc = PipelineDesc { uniforms: MOVED, ..desc() };
+        assert_eq!(
+            validate(&module, &desc),
+            vec![
+                "`vs` uniform `globals` at binding 2 is not bound".to_string(),
+                "uniform binding 1 is not used by `vs` or `fs`".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_offset() {
+        const PACKED: &[BlockField] = &[BlockField { name: "time", offset: 0 }, BlockField { name: "tint", offset: 4 }];
+        const UNIFORMS: &[UniformDesc] = &[UniformDesc { binding: 2, size: 32, fields: PACKED }];
+        let module = Module::parse(&bytes(&module())).unwrap();
+        let desc = PipelineDesc { uniforms: UNIFORMS, ..desc() };
+        assert_eq!(
+            validate(&module, &desc),
+            vec!["`vs` uniform at binding 2: `tint` is at offset 4 on the host, 16 in the shader".to_string()]
+        );
+    }
+}
@@ -0,0 +1,3 @@
+// SPIR-V modules built by `build.rs`, one constant per shader crate, e.g. `SHADER` for
+// `shader/`. `--hot-reload` replaces them at runtime
+include!(concat!(env!("OUT_DIR"), "/spirv.rs"));
@@ -0,0 +1,137 @@
+use anyhow::{Result};
+use shader::math::saturate;
+
+use crate::camera;
+use crate::effect;
+use crate::gradient;
+use crate::palette;
+use crate::particles;
+
+struct Slot {
+    effect: Box<dyn effect::Effect>,
+    buffers: particles::ParticleBuffers,
+    // library palette the effect was last given
+    palette: String,
+    start: f32,
+    parallel: bool,
+}
+
+impl Slot {
+    fn new(grr: &grr::Device, effect: Box<dyn effect::Effect>, settings: &effect::Settings, start: f32) -> Result<Self> {
+        let buffers = particles::ParticleBuffers::new(grr, effect.capacity())?;
+        let palette = settings.palette_name(effect.name());
+        Ok(Slot {
+            effect: effect,
+            buffers: buffers,
+            palette: palette,
+            start: start,
+            parallel: !settings.single_threaded,
+        })
+    }
+
+    fn update(&mut self, grr: &grr::Device, renderer: &particles::ParticleRenderer, camera: &camera::Camera, alpha: f32, time: f32) {
+        let effect = &mut self.effect;
+        let local_time = time - self.start;
+        let parallel = self.parallel;
+        effect.set_alpha(alpha);
+        self.buffers.write(grr, |particles| {
+            particles.set_parallel(parallel);
+            effect.update(particles, local_time);
+        });
+        renderer.draw(grr, camera, &self.buffers, effect.dof());
+    }
+
+    fn destroy(&mut self, grr: &grr::Device) {
+        self.buffers.destroy(grr);
+    }
+}
+
+// owns the running effect, and the outgoing one while a crossfade is in progress
+pub struct Switcher {
+    current: Slot,
+    previous: Option<Slot>,
+    fade_start: f32,
+    crossfade: f32,
+}
+
+impl Switcher {
+    pub fn new(grr: &grr::Device, effect: Box<dyn effect::Effect>, settings: &effect::Settings, crossfade: f32, time: f32) -> Result<Self> {
+        Ok(Switcher {
+            current: Slot::new(grr, effect, settings, time)?,
+            previous: None,
+            fade_start: time,
+            crossfade: crossfade.max(0.0),
+        })
+    }
+
+    pub fn current(&self) -> &'static str {
+        self.current.effect.name()
+    }
+
+    // restarts the local time of the current effect
+    pub fn restart(&mut self, time: f32) {
+        self.current.start = time;
+    }
+
+    pub fn switch_to(&mut self, grr: &grr::Device, name: &str, settings: &effect::Settings, time: f32) -> Result<()> {
+        if name == self.current() {
+            return Ok(());
+        }
+        let slot = Slot::new(grr, effect::create(name, settings)?, settings, time)?;
+
+        // a switch during a crossfade drops the oldest effect right away
+        if let Some(mut previous) = self.previous.take() {
+            previous.destroy(grr);
+        }
+        let current = std::mem::replace(&mut self.current, slot);
+        self.previous = Some(current);
+        self.fade_start = time;
+        Ok(())
+    }
+
+    pub fn switch_next(&mut self, grr: &grr::Device, settings: &effect::Settings, time: f32) -> Result<()> {
+        let names = effect::names();
+        let index = names.iter().position(|name| *name == self.current()).unwrap_or(0);
+        let name = names[(index + 1) % names.len()];
+        self.switch_to(grr, name, settings, time)
+    }
+
+    // gives the current effect `gradient`, remembered as library palette `name` for `next_palette`
+    pub fn set_palette(&mut self, name: &str, gradient: gradient::Gradient) {
+        self.current.effect.set_gradient(gradient);
+        self.current.palette = name.to_string();
+    }
+
+    // gives the current effect the palette after its last one in the library
+    pub fn next_palette(&mut self, library: &palette::Library) -> Option<&str> {
+        let next = library.next(&self.current.palette)?;
+        self.set_palette(&next.name, next.gradient.clone());
+        Some(&self.current.palette)
+    }
+
+    pub fn update(&mut self, grr: &grr::Device, renderer: &particles::ParticleRenderer, camera: &camera::Camera, time: f32) {
+        let fade = if self.previous.is_some() && self.crossfade > 0.0 {
+            saturate((time - self.fade_start) / self.crossfade)
+        } else {
+            1.0
+        };
+
+        if let Some(previous) = &mut self.previous {
+            previous.update(grr, renderer, camera, 1.0 - fade, time);
+        }
+        self.current.update(grr, renderer, camera, fade, time);
+
+        if fade >= 1.0 {
+            if let Some(mut previous) = self.previous.take() {
+                previous.destroy(grr);
+            }
+        }
+    }
+
+    pub fn destroy(&mut self, grr: &grr::Device) {
+        if let Some(mut previous) = self.previous.take() {
+            previous.destroy(grr);
+        }
+        self.current.destroy(grr);
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::effect;
+use crate::palette::{PaletteRef};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CameraParams {
+    pub fov: Option<f32>,
+    pub distance: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scene {
+    pub start: f32,
+    pub end: f32,
+    pub effect: String,
+    pub palette: Option<PaletteRef>,
+    pub sky: Option<[f32; 3]>,
+    pub camera: Option<CameraParams>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Timeline {
+    #[serde(default, rename = "loop")]
+    pub looping: bool,
+    #[serde(rename = "scene")]
+    pub scenes: Vec<Scene>,
+}
+
+// a scene active at a given time, with its start mapped back to the caller's clock
+pub struct Cue<'a> {
+    pub index: usize,
+    pub scene: &'a Scene,
+    pub start: f32,
+}
+
+impl Timeline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read timeline {}", path.display()))?;
+        let timeline: Timeline = toml::from_str(&text)
+            .with_context(|| format!("failed to parse timeline {}", path.display()))?;
+        timeline.validate()?;
+        Ok(timeline)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.scenes.is_empty() {
+            return Err(anyhow!("timeline has no scenes"));
+        }
+        let mut last_end = 0.0;
+        for (i, scene) in self.scenes.iter().enumerate() {
+            effect::lookup(&scene.effect).with_context(|| format!("scene {}", i))?;
+            if scene.end <= scene.start {
+                return Err(anyhow!("scene {} ends before it starts", i));
+            }
+            if scene.start < last_end {
+                return Err(anyhow!("scene {} overlaps the previous scene", i));
+            }
+            last_end = scene.end;
+        }
+        Ok(())
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.scenes.last().map(|scene| scene.end).unwrap_or(0.0)
+    }
+
+    // start of the current loop on the caller's clock and `time` within it
+    fn local(&self, time: f32) -> (f32, f32) {
+        let duration = self.duration();
+        if self.looping && duration > 0.0 {
+            let t = time.rem_euclid(duration);
+            (time - t, t)
+        } else {
+            (0.0, time)
+        }
+    }
+
+    // `None` in the gaps between scenes, before the first one and after the last one of a
+    // timeline that doesn't loop
+    pub fn cue(&self, time: f32) -> Option<Cue<'_>> {
+        let (offset, t) = self.local(time);
+
+        let index = self.scenes.iter().rposition(|scene| scene.start <= t)?;
+        let scene = &self.scenes[index];
+        if t >= scene.end {
+            return None;
+        }
+        Some(Cue {
+            index: index,
+            scene: scene,
+            start: offset + scene.start,
+        })
+    }
+
+    // clock time the scene after the one at `time` starts, the first scene of the next loop
+    // after the last one, `None` past the last scene of a timeline that doesn't loop
+    pub fn next_start(&self, time: f32) -> Option<f32> {
+        let (offset, t) = self.local(time);
+        match self.scenes.iter().find(|scene| scene.start > t) {
+            Some(scene) => Some(offset + scene.start),
+            None if self.looping => Some(offset + self.duration() + self.scenes[0].start),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeline(looping: bool) -> Timeline {
+        let scene = |start: f32, end: f32| Scene {
+            start: start,
+            end: end,
+            effect: effect::DEFAULT.to_string(),
+            palette: None,
+            sky: None,
+            camera: None,
+        };
+        Timeline {
+            looping: looping,
+            scenes: vec![scene(0.0, 4.0), scene(4.0, 10.0), scene(12.0, 16.0)],
+        }
+    }
+
+    #[test]
+    fn next_start() {
+        let once = timeline(false);
+        assert_eq!(once.next_start(0.0), Some(4.0));
+        assert_eq!(once.next_start(4.0), Some(12.0));
+        assert_eq!(once.next_start(11.0), Some(12.0));
+        assert_eq!(once.next_start(12.0), None);
+
+        let looping = timeline(true);
+        assert_eq!(looping.next_start(13.0), Some(16.0));
+        assert_eq!(looping.next_start(17.0), Some(20.0));
+    }
+
+    #[test]
+    fn cue_loops() {
+        let looping = timeline(true);
+        let cue = looping.cue(21.0).unwrap();
+        assert_eq!((cue.index, cue.start), (1, 20.0));
+    }
+
+    #[test]
+    fn cue_honors_end() {
+        let once = timeline(false);
+        assert_eq!(once.cue(0.0).unwrap().index, 0);
+        assert_eq!(once.cue(4.0).unwrap().index, 1);
+        assert_eq!(once.cue(9.5).unwrap().index, 1);
+        // the gap and the time past the last scene are blank
+        assert!(once.cue(10.0).is_none());
+        assert!(once.cue(11.0).is_none());
+        assert_eq!(once.cue(12.0).unwrap().index, 2);
+        assert!(once.cue(16.0).is_none());
+        assert!(once.cue(-1.0).is_none());
+
+        let looping = timeline(true);
+        assert!(looping.cue(27.0).is_none());
+        assert_eq!(looping.cue(29.0).unwrap().start, 28.0);
+    }
+}
@@ -0,0 +1,15 @@
+use std::path::Path;
+
+use rust_snippets::{effect, golden, palette};
+
+// `check` against the committed goldens, with the command's default tolerance
+#[test]
+fn goldens() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let settings = effect::Settings {
+        palettes: palette::Library::load(&root.join(palette::DEFAULT_PATH)).unwrap(),
+        ..Default::default()
+    };
+    let tolerance = golden::Tolerance { rmse: 0.01, max: 0.25 };
+    golden::check(&settings, &root.join(golden::DEFAULT_DIR), &root.join(golden::DEFAULT_OUTPUT), tolerance, false).unwrap();
+}